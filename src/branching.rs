@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::{Reaction, Situation};
+
+// ===================== Ветвления сценария =====================
+//
+// Ситуация может объявить `branches`: победившая пара -> id следующей ситуации.
+// Если пары в ветках нет — идём дальше по колоде, пропуская `branch_only`
+// ситуации (в них попадают только по ветке). После конца колоды — снова первая.
+
+fn find(situations: &[Situation], id: &str) -> Option<usize> {
+    situations.iter().position(|s| s.id.as_deref() == Some(id))
+}
+
+/// Следующая по порядку колоды ситуация без учёта ветвлений и без заворота в начало.
+fn linear_next(situations: &[Situation], from: usize) -> Option<usize> {
+    (from + 1..situations.len()).find(|&i| !situations[i].branch_only)
+}
+
+fn first(situations: &[Situation]) -> usize {
+    situations.iter().position(|s| !s.branch_only).unwrap_or(0)
+}
+
+/// Куда ведёт «Дальше» после ситуации `from`, если зал выбрал `pair`.
+/// Второе значение — сработала ли ветка.
+pub fn next_index(
+    situations: &[Situation],
+    from: usize,
+    pair: (Reaction, Reaction),
+) -> (usize, bool) {
+    if let Some(target) = situations
+        .get(from)
+        .and_then(|s| s.branches.get(&pair))
+        .and_then(|id| find(situations, id))
+    {
+        return (target, true);
+    }
+    (
+        linear_next(situations, from).unwrap_or_else(|| first(situations)),
+        false,
+    )
+}
+
+// все три возможные пары
+const PAIRS: [(Reaction, Reaction); 3] = [
+    (Reaction::Lie, Reaction::Delay),
+    (Reaction::Lie, Reaction::Freeze),
+    (Reaction::Delay, Reaction::Freeze),
+];
+
+fn edges(situations: &[Situation], from: usize) -> Vec<usize> {
+    let s = &situations[from];
+    let mut out: Vec<usize> = s
+        .branches
+        .values()
+        .filter_map(|id| find(situations, id))
+        .collect();
+    // линейный переход есть, только если ветки покрывают не все пары
+    if PAIRS.iter().any(|p| !s.branches.contains_key(p)) {
+        out.extend(linear_next(situations, from));
+    }
+    out
+}
+
+/// Проверка колоды: ссылки, дубли id, недостижимые ситуации и циклы.
+/// Заворот в начало после конца колоды циклом не считается — это обычный повтор.
+pub fn validate(situations: &[Situation]) -> Result<(), String> {
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    for (i, s) in situations.iter().enumerate() {
+        if let Some(id) = s.id.as_deref() {
+            if let Some(prev) = ids.insert(id, i) {
+                return Err(format!(
+                    "duplicate situation id \"{id}\" (#{prev} and #{i})"
+                ));
+            }
+        }
+    }
+    for (i, s) in situations.iter().enumerate() {
+        for (pair, id) in &s.branches {
+            if !ids.contains_key(id.as_str()) {
+                return Err(format!(
+                    "situation #{i} \"{}\": branch {pair:?} points to unknown id \"{id}\"",
+                    s.title
+                ));
+            }
+        }
+    }
+    if situations.is_empty() {
+        return Ok(());
+    }
+    if situations[0].branch_only {
+        return Err("the first situation cannot be branch_only".to_string());
+    }
+
+    // обход в глубину: серый — в текущем пути (повторный заход = цикл), чёрный — разобран
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        White,
+        Grey,
+        Black,
+    }
+    let mut marks = vec![Mark::White; situations.len()];
+    let mut stack = vec![(0usize, edges(situations, 0))];
+    marks[0] = Mark::Grey;
+    while let Some((node, pending)) = stack.last_mut() {
+        match pending.pop() {
+            Some(next) => match marks[next] {
+                Mark::Grey => {
+                    return Err(format!(
+                        "cycle: situation #{node} \"{}\" leads back to #{next} \"{}\"",
+                        situations[*node].title, situations[next].title
+                    ))
+                }
+                Mark::White => {
+                    marks[next] = Mark::Grey;
+                    stack.push((next, edges(situations, next)));
+                }
+                Mark::Black => {}
+            },
+            None => {
+                marks[*node] = Mark::Black;
+                stack.pop();
+            }
+        }
+    }
+
+    if let Some(i) = marks.iter().position(|m| *m == Mark::White) {
+        return Err(format!(
+            "situation #{i} \"{}\" is unreachable",
+            situations[i].title
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+// ===================== Обсуждение после ответа =====================
+//
+// Таймер на разговор после показа. Включается вручную из админки или сам на
+// каждом показе, если задана длительность по умолчанию (DISCUSSION_SECS).
+// Отсчёт идёт с момента, когда ответ виден всем, — после этапов показа.
+
+#[derive(Clone, Debug)]
+pub struct Discussion {
+    requested: Instant,
+    secs: u64,
+}
+
+/// Что показывают игроки и проектор.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Status {
+    pub secs: u64,
+    pub left_ms: u64,
+    // ответ ещё раскрывается — таймер пока не пошёл
+    pub pending: bool,
+    pub over: bool,
+}
+
+impl Discussion {
+    pub fn start(requested: Instant, secs: u64) -> Self {
+        Discussion { requested, secs }
+    }
+
+    // ведущий мог промотать этапы показа, поэтому момент открытия ответа
+    // передаём при каждом вызове, а не запоминаем
+    fn starts(&self, answer_at: Option<Instant>) -> Instant {
+        answer_at.map_or(self.requested, |at| at.max(self.requested))
+    }
+
+    pub fn status(&self, answer_at: Option<Instant>, now: Instant) -> Status {
+        let starts = self.starts(answer_at);
+        let total = Duration::from_secs(self.secs);
+        let left = total
+            .saturating_sub(now.saturating_duration_since(starts))
+            .as_millis() as u64;
+        Status {
+            secs: self.secs,
+            left_ms: left,
+            pending: now < starts,
+            over: left == 0,
+        }
+    }
+
+    /// Сколько реально обсуждали — для истории сессии.
+    pub fn spent_secs(&self, answer_at: Option<Instant>, now: Instant) -> u64 {
+        now.saturating_duration_since(self.starts(answer_at))
+            .as_secs()
+            .min(self.secs)
+    }
+}
@@ -0,0 +1,94 @@
+use std::{collections::VecDeque, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+// ===================== Живые эмодзи-реакции =====================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Emoji {
+    Facepalm,
+    Laugh,
+    Fire,
+}
+
+impl Emoji {
+    fn idx(self) -> usize {
+        match self {
+            Emoji::Facepalm => 0,
+            Emoji::Laugh => 1,
+            Emoji::Fire => 2,
+        }
+    }
+}
+
+// сколько секунд истории держим для отстающих экранов
+const KEEP_SECONDS: u64 = 30;
+
+#[derive(Clone, Serialize)]
+pub struct Bucket {
+    pub second: u64,
+    pub counts: [u32; 3], // [facepalm, laugh, fire]
+}
+
+/// Поток реакций, сгруппированный по секундам. На голосование не влияет.
+#[derive(Clone)]
+pub struct EmojiStream {
+    started: Instant,
+    buckets: VecDeque<Bucket>,
+}
+
+impl Default for EmojiStream {
+    fn default() -> Self {
+        EmojiStream {
+            started: Instant::now(),
+            buckets: VecDeque::new(),
+        }
+    }
+}
+
+impl EmojiStream {
+    pub fn now(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    pub fn push(&mut self, emoji: Emoji) {
+        self.push_at(self.now(), emoji);
+    }
+
+    pub fn push_at(&mut self, second: u64, emoji: Emoji) {
+        match self.buckets.back_mut() {
+            Some(b) if b.second == second => b.counts[emoji.idx()] += 1,
+            _ => {
+                let mut counts = [0; 3];
+                counts[emoji.idx()] = 1;
+                self.buckets.push_back(Bucket { second, counts });
+            }
+        }
+        while self
+            .buckets
+            .front()
+            .is_some_and(|b| b.second + KEEP_SECONDS < second)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Закрытые секунды после `since`: текущую не отдаём, пока она не закончилась,
+    /// иначе экран показал бы её дважды.
+    pub fn since(&self, since: Option<u64>) -> Vec<Bucket> {
+        self.since_at(self.now(), since)
+    }
+
+    pub fn since_at(&self, now: u64, since: Option<u64>) -> Vec<Bucket> {
+        self.buckets
+            .iter()
+            .filter(|b| b.second < now && since.is_none_or(|s| b.second > s))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+    }
+}
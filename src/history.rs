@@ -0,0 +1,186 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Reaction;
+
+// ===================== История сессии =====================
+//
+// По записи на каждый заход в ситуацию: что показали, сколько обсуждали,
+// какие заметки и договорённости ведущий к ней записал. Уходит в экспорт.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteKind {
+    Note,
+    Action,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Note {
+    pub id: u64,
+    pub kind: NoteKind,
+    pub text: String,
+    // договорённость уже заведена в трекере (см. tracker.rs)
+    #[serde(default)]
+    pub pushed: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Shown {
+    pub answer: String,
+    pub pair: Option<(Reaction, Reaction)>,
+    pub counts: [u64; 3],
+    // ведущий поправил пару, вариант или текст — ответ не выбран залом
+    #[serde(default)]
+    pub overridden: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub situation: usize,
+    pub title: String,
+    #[serde(default)]
+    pub shown: Option<Shown>,
+    #[serde(default)]
+    pub discussion_secs: Option<u64>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    // запись текущей ситуации; после «Дальше» закрыта
+    #[serde(default)]
+    pub open: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    entries: Vec<Entry>,
+    next_note_id: u64,
+}
+
+pub const MAX_NOTE_LEN: usize = 500;
+
+impl History {
+    /// Запись текущей ситуации; заводится при первом обращении.
+    pub fn current(&mut self, situation: usize, title: &str) -> &mut Entry {
+        let reuse = self
+            .entries
+            .last()
+            .is_some_and(|e| e.open && e.situation == situation);
+        if !reuse {
+            self.close();
+            self.entries.push(Entry {
+                situation,
+                title: title.to_string(),
+                shown: None,
+                discussion_secs: None,
+                notes: Vec::new(),
+                open: true,
+            });
+        }
+        let last = self.entries.len() - 1;
+        &mut self.entries[last]
+    }
+
+    /// Запись текущей ситуации, если она уже есть.
+    pub fn open_entry(&self) -> Option<&Entry> {
+        self.entries.last().filter(|e| e.open)
+    }
+
+    pub fn open_entry_mut(&mut self) -> Option<&mut Entry> {
+        self.entries.last_mut().filter(|e| e.open)
+    }
+
+    /// Ситуация пройдена: следующая заметка уйдёт уже в новую запись.
+    pub fn close(&mut self) {
+        if let Some(e) = self.entries.last_mut() {
+            e.open = false;
+        }
+    }
+
+    pub fn add_note(
+        &mut self,
+        situation: usize,
+        title: &str,
+        kind: NoteKind,
+        text: &str,
+    ) -> Result<Note, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Пустая заметка".to_string());
+        }
+        if text.chars().count() > MAX_NOTE_LEN {
+            return Err(format!("Заметка длиннее {MAX_NOTE_LEN} символов"));
+        }
+        self.next_note_id += 1;
+        let note = Note {
+            id: self.next_note_id,
+            kind,
+            text: text.to_string(),
+            pushed: false,
+        };
+        self.current(situation, title).notes.push(note.clone());
+        Ok(note)
+    }
+
+    /// Удаление по id из любой записи (ведущий ошибся).
+    pub fn remove_note(&mut self, id: u64) -> bool {
+        for e in &mut self.entries {
+            if let Some(i) = e.notes.iter().position(|n| n.id == id) {
+                e.notes.remove(i);
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn mark_pushed(&mut self, id: u64) {
+        for e in &mut self.entries {
+            if let Some(n) = e.notes.iter_mut().find(|n| n.id == id) {
+                n.pushed = true;
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Экспорт для вставки в вики или чат.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Итоги сессии\n");
+        for (n, e) in self.entries.iter().enumerate() {
+            let _ = write!(out, "\n## {}. {}\n\n", n + 1, e.title);
+            if let Some(s) = &e.shown {
+                let _ = writeln!(
+                    out,
+                    "Голоса (врать, отложить, заморозить): {}, {}, {}",
+                    s.counts[0], s.counts[1], s.counts[2]
+                );
+                let _ = writeln!(out, "\n> {}", s.answer);
+                if s.overridden {
+                    let _ = writeln!(out, "\n_Ответ поправлен ведущим_");
+                }
+            }
+            if let Some(secs) = e.discussion_secs {
+                let _ = writeln!(out, "\nОбсуждали: {}:{:02}", secs / 60, secs % 60);
+            }
+            let section = |out: &mut String, kind: NoteKind, heading: &str| {
+                let mut notes = e.notes.iter().filter(|n| n.kind == kind).peekable();
+                if notes.peek().is_some() {
+                    let _ = writeln!(out, "\n{heading}:\n");
+                    for note in notes {
+                        let mark = if kind == NoteKind::Action {
+                            "- [ ] "
+                        } else {
+                            "- "
+                        };
+                        let _ = writeln!(out, "{mark}{}", note.text);
+                    }
+                }
+            };
+            section(&mut out, NoteKind::Note, "Заметки");
+            section(&mut out, NoteKind::Action, "Договорились");
+        }
+        out
+    }
+}
@@ -0,0 +1,105 @@
+use axum::http::{header, HeaderMap};
+use qrcode::{render::svg, EcLevel, QrCode};
+
+use crate::random_u64;
+
+// ===================== Вход в комнату: код и QR =====================
+
+// без 0/O, 1/I/L — чтобы код легко читался со слайда и вводился с телефона
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+const CODE_LEN: usize = 4;
+
+// размер модуля QR в пикселях для PNG и ширина белой рамки в модулях
+const PNG_MODULE_PX: usize = 8;
+const QUIET_ZONE: usize = 4;
+
+pub fn generate_room_code() -> String {
+    let mut n = random_u64();
+    (0..CODE_LEN)
+        .map(|_| {
+            let c = CODE_ALPHABET[(n % CODE_ALPHABET.len() as u64) as usize];
+            n /= CODE_ALPHABET.len() as u64;
+            c as char
+        })
+        .collect()
+}
+
+/// Код комнаты из ROOM_CODE: не задан — генерируем, "off" — без кода.
+pub fn room_code_from_env() -> Option<String> {
+    match std::env::var("ROOM_CODE") {
+        Ok(v) if v.eq_ignore_ascii_case("off") || v.trim().is_empty() => None,
+        Ok(v) => Some(normalize_code(&v)),
+        Err(_) => Some(generate_room_code()),
+    }
+}
+
+/// Игроки вводят код как попало: пробелы, строчные буквы.
+pub fn normalize_code(raw: &str) -> String {
+    raw.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Базовый адрес сервера: PUBLIC_URL, если задан, иначе из заголовка Host.
+pub fn base_url(public_url: Option<&str>, headers: &HeaderMap) -> String {
+    if let Some(url) = public_url {
+        return url.trim_end_matches('/').to_string();
+    }
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost:3000");
+    format!("http://{host}")
+}
+
+/// Ссылка для игроков: короткая /r/КОД, если код есть, иначе просто корень.
+pub fn player_url(base: &str, code: Option<&str>) -> String {
+    match code {
+        Some(code) => format!("{base}/r/{code}"),
+        None => format!("{base}/"),
+    }
+}
+
+fn qr(data: &str) -> Result<QrCode, qrcode::types::QrError> {
+    QrCode::with_error_correction_level(data, EcLevel::M)
+}
+
+pub fn qr_svg(data: &str) -> Result<String, qrcode::types::QrError> {
+    Ok(qr(data)?
+        .render::<svg::Color>()
+        .min_dimensions(256, 256)
+        .quiet_zone(true)
+        .build())
+}
+
+pub fn qr_png(data: &str) -> Result<Vec<u8>, String> {
+    let code = qr(data).map_err(|e| e.to_string())?;
+    let width = code.width();
+    let modules = code.to_colors();
+    let side = (width + 2 * QUIET_ZONE) * PNG_MODULE_PX;
+
+    // 8-битный grayscale: 0 — чёрный модуль, 255 — фон
+    let mut pixels = vec![255u8; side * side];
+    for (i, color) in modules.iter().enumerate() {
+        if *color != qrcode::Color::Dark {
+            continue;
+        }
+        let (mx, my) = (i % width + QUIET_ZONE, i / width + QUIET_ZONE);
+        for y in my * PNG_MODULE_PX..(my + 1) * PNG_MODULE_PX {
+            let row = y * side;
+            pixels[row + mx * PNG_MODULE_PX..row + (mx + 1) * PNG_MODULE_PX].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut enc = png::Encoder::new(&mut out, side as u32, side as u32);
+    enc.set_color(png::ColorType::Grayscale);
+    enc.set_depth(png::BitDepth::Eight);
+    let mut writer = enc.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(out)
+}
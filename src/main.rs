@@ -1,21 +1,67 @@
 use std::{
-    collections::HashMap,
+    any::Any,
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
     net::SocketAddr,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
 };
 
 use axum::{
-    extract::State,
-    response::Html,
-    routing::{get, post},
+    body::Bytes,
+    extract::{rejection::JsonRejection, ConnectInfo, MatchedPath, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{delete, get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
+};
+use tracing::{error, info, warn, Level};
+use tracing_subscriber::EnvFilter;
+
+mod branching;
+mod discussion;
+mod emoji;
+mod history;
+mod join;
+mod meters;
+mod metrics;
+mod persist;
+mod players;
+mod questions;
+mod ranking;
+mod reveal;
+mod rules;
+mod template;
+#[cfg(test)]
+mod tests;
+mod tracker;
+mod variants;
+mod winner;
+
+use discussion::Discussion;
+use emoji::{Bucket, Emoji, EmojiStream};
+use history::{History, Note, NoteKind};
+use meters::{Bound, Change, Ending, EndingView, MeterDef, MeterView, Meters};
+use metrics::Metrics;
+use players::{LeaderboardEntry, Players, Prediction};
+use questions::{Question, QuestionBox, QuestionStatus};
+use ranking::{Ballots, Tally, VotingMode};
+use reveal::Reveal;
+use rules::Rule;
+use variants::{VariantPicker, VariantStrategy};
+use winner::WinnerRule;
 
 // ===================== Доменные типы =====================
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum Reaction {
     Lie,
     Delay,
@@ -33,20 +79,168 @@ impl Reaction {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 struct Situation {
     title: String,
     description: String,
-    // ключ: (Reaction, Reaction) — отсортирован
-    answers: HashMap<(Reaction, Reaction), String>,
+    // ключ: (Reaction, Reaction) — отсортирован; значение — пул вариантов ответа
+    answers: HashMap<(Reaction, Reaction), Vec<String>>,
+    // для ветвлений: на ситуацию ссылаются по id
+    id: Option<String>,
+    // победившая пара -> id следующей ситуации (см. branching.rs)
+    branches: HashMap<(Reaction, Reaction), String>,
+    // по порядку колоды пропускается, попасть можно только по ветке
+    branch_only: bool,
+    // пара -> сдвиги шкал компании (см. meters.rs)
+    effects: HashMap<(Reaction, Reaction), Vec<(String, i64)>>,
+    // своя стратегия выбора пары вместо комнатной (см. winner.rs)
+    winner: Option<WinnerRule>,
+    // условия, которые проверяются раньше пар (см. rules.rs)
+    rules: Vec<Rule>,
+    // шпаргалка ведущего; игрокам не отдаётся
+    script: Script,
+}
+
+/// Заметки ведущего к ситуации: видны только в админке.
+#[derive(Clone, Debug, Default, Serialize)]
+struct Script {
+    notes: String,
+    // вопросы, которыми можно раскачать обсуждение
+    prompts: Vec<String>,
+    // сколько примерно держать ситуацию, в секундах
+    duration_secs: Option<u64>,
+}
+
+impl Situation {
+    /// Первое сработавшее правило при таком раскладе голосов.
+    fn matched_rule(&self, vars: &template::Vars) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.matches(vars))
+    }
+
+    /// Текст ответа: по правилу, если какое-то сработало, иначе вариант из пула
+    /// для пары. Данные голосования подставлены.
+    fn answer_for(
+        &self,
+        key: (Reaction, Reaction),
+        variant: usize,
+        vars: &template::Vars,
+    ) -> String {
+        match self.matched_rule(vars) {
+            Some(rule) => template::render(&rule.answer, vars),
+            None => self.pair_answer(key, variant, vars),
+        }
+    }
+
+    /// Ответ строго по паре, без правил.
+    fn pair_answer(
+        &self,
+        key: (Reaction, Reaction),
+        variant: usize,
+        vars: &template::Vars,
+    ) -> String {
+        match self.answers.get(&key) {
+            Some(pool) if !pool.is_empty() => template::render(&pool[variant % pool.len()], vars),
+            _ if self.answers.is_empty() => FREE_DISCUSSION_ANSWER.to_string(),
+            _ => "Ответ не найден для этой комбинации".to_string(),
+        }
+    }
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct ShownResult {
     situation_title: String,
     answer: String,
+    // какой вариант из пула показан и сколько их всего
+    #[serde(default)]
+    variant: usize,
+    #[serde(default)]
+    variants: usize,
+    // в ранжированном режиме — очки метода, а не клики
+    counts: [u64; 3],
+    version: u64,
+    // пара, по которой выбран ответ (от неё же зависит ветка сценария)
+    #[serde(default)]
+    pair: Option<(Reaction, Reaction)>,
+    // какой стратегией выбрана пара
+    #[serde(default)]
+    selection: WinnerRule,
+    // условие правила, если ответ выбран по нему, а не по паре
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+    // ведущий поправил пару, вариант или текст ответа
+    #[serde(default)]
+    overridden: bool,
+    // после показа ведущий может раскрыть ответы на все пары
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comparison: Option<Comparison>,
+    // только в режиме прогнозов
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    leaderboard: Option<Vec<LeaderboardEntry>>,
+    // только в командном режиме: как голосовала каждая команда
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    teams: Option<Vec<TeamResult>>,
+    // как ответ сдвинул шкалы компании и не закончилась ли на этом игра
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    changes: Vec<Change>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ending: Option<EndingView>,
+    // только в ранжированном режиме: очки, места и попарные сравнения
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ranking: Option<Tally>,
+}
+
+/// Все заготовленные ответы ситуации рядом — «а что было бы, если».
+#[derive(Clone, Serialize, Deserialize)]
+struct Comparison {
+    // своя версия: сравнение публикуется поверх уже показанного ответа
+    version: u64,
+    entries: Vec<ComparisonEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ComparisonEntry {
+    pair: (Reaction, Reaction),
+    // все варианты ответа на пару
+    answers: Vec<String>,
+    // голоса за обе реакции пары и их доля от всех голосов, %
+    votes: u64,
+    share: u64,
+    chosen: bool,
+}
+
+/// Ответ для игроков вместе с этапом показа.
+#[derive(Serialize)]
+struct PublicResult {
+    #[serde(flatten)]
+    result: PublicView,
+    reveal: reveal::Status,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PublicView {
+    Staged(StagedResult),
+    Shown(ShownResult),
+}
+
+/// Пока идут этапы показа: голоса уже видны, а выбранная пара и ответ — ещё нет.
+#[derive(Serialize)]
+struct StagedResult {
+    situation_title: String,
+    // пустой: клиенты держат под ответ место
+    answer: String,
     counts: [u64; 3],
     version: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ranking: Option<Tally>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct TeamResult {
+    name: String,
+    counts: [u64; 3],
+    pair: (Reaction, Reaction),
+    answer: String,
 }
 
 #[derive(Clone)]
@@ -56,39 +250,483 @@ struct AppState {
     counts: [u64; 3], // [lie, delay, freeze]
     last_result: Option<ShownResult>,
     result_version: u64,
+    metrics: Metrics,
+    // объявление для игроков (например, о перезапуске сервера)
+    notice: Option<String>,
+    // короткий код комнаты, который можно ввести руками
+    room_code: Option<String>,
+    // внешний адрес для ссылок и QR (PUBLIC_URL), иначе берём Host
+    public_url: Option<String>,
+    variants: VariantPicker,
+    questions: QuestionBox,
+    // вопросы, вставленные в колоду, — по порядку вставки, чтобы повторить после перезапуска
+    promoted: Vec<Promotion>,
+    players: Players,
+    // игроки заодно угадывают, какую пару выберет зал
+    prediction_mode: bool,
+    // командный режим: пустой список — выключен
+    teams: Vec<String>,
+    team_counts: Vec<[u64; 3]>, // по индексу команды, как counts
+    emoji: EmojiStream,
+    meters: Meters,
+    voting: VotingMode,
+    ballots: Ballots,
+    winner: WinnerRule,
+    // этапы показа ответа и идущий показ
+    reveal_config: reveal::Config,
+    reveal: Option<Reveal>,
+    // обсуждение после ответа: длительность по умолчанию (тогда стартует само) и идущее
+    discussion_secs: Option<u64>,
+    discussion: Option<Discussion>,
+    history: History,
+    // выгрузка договорённостей: шаблон запроса (TRACKER_CONFIG) и последний отчёт
+    tracker: Option<Arc<tracker::Config>>,
+    tracker_busy: bool,
+    tracker_report: Option<tracker::Report>,
+}
+
+impl AppState {
+    fn new(situations: Vec<Situation>) -> Self {
+        AppState {
+            situations,
+            current_index: 0,
+            counts: [0, 0, 0],
+            last_result: None,
+            result_version: 0,
+            metrics: Metrics::default(),
+            notice: None,
+            room_code: None,
+            public_url: None,
+            variants: VariantPicker::new(VariantStrategy::LeastRecentlyShown),
+            questions: QuestionBox::default(),
+            promoted: Vec::new(),
+            players: Players::default(),
+            prediction_mode: false,
+            teams: Vec::new(),
+            team_counts: Vec::new(),
+            emoji: EmojiStream::default(),
+            meters: Meters::default(),
+            voting: VotingMode::default(),
+            ballots: Ballots::default(),
+            winner: WinnerRule::default(),
+            reveal_config: reveal::Config::default(),
+            reveal: None,
+            discussion_secs: None,
+            discussion: None,
+            history: History::default(),
+            tracker: None,
+            tracker_busy: false,
+            tracker_report: None,
+        }
+    }
+
+    fn template_vars(&self) -> template::Vars {
+        self.template_vars_for(self.counts)
+    }
+
+    fn template_vars_for(&self, counts: [u64; 3]) -> template::Vars {
+        template::Vars {
+            counts,
+            player_count: self.metrics.connected_players(),
+        }
+    }
+
+    fn set_teams(&mut self, teams: Vec<String>) {
+        if teams != self.teams {
+            self.players.assign_teams(teams.len());
+        }
+        self.team_counts = vec![[0, 0, 0]; teams.len()];
+        self.teams = teams;
+    }
+
+    /// Итоги по командам считаются только из кликов: бюллетени командам не копятся.
+    fn team_results(&self) -> bool {
+        !self.teams.is_empty() && !self.voting.is_ranked()
+    }
+
+    fn reset_counts(&mut self) {
+        self.counts = [0, 0, 0];
+        self.team_counts.iter_mut().for_each(|c| *c = [0, 0, 0]);
+        self.ballots.clear();
+    }
+
+    fn reveal_status(&self) -> reveal::Status {
+        self.reveal.as_ref().map_or(reveal::Status::DONE, |r| {
+            r.status(&self.reveal_config, Instant::now())
+        })
+    }
+
+    // момент, когда ответ увидят все; без идущего показа — неизвестен
+    fn answer_at(&self) -> Option<Instant> {
+        self.reveal
+            .as_ref()
+            .map(|r| r.answer_at(&self.reveal_config))
+    }
+
+    fn discussion_status(&self) -> Option<discussion::Status> {
+        self.discussion
+            .as_ref()
+            .map(|d| d.status(self.answer_at(), Instant::now()))
+    }
+
+    /// Обсуждение закончено: сколько говорили, записываем в историю.
+    fn end_discussion(&mut self) {
+        if let Some(d) = self.discussion.take() {
+            let spent = d.spent_secs(self.answer_at(), Instant::now());
+            if let Some(entry) = self.history.open_entry_mut() {
+                entry.discussion_secs = Some(spent);
+            }
+        }
+    }
+
+    /// Показанный ответ глазами игроков: до последнего этапа — только голоса.
+    fn public_result(&self) -> Option<PublicResult> {
+        let status = self.reveal_status();
+        let shown = self.last_result.clone()?;
+        let result = if status.stage < reveal::Stage::Answer {
+            PublicView::Staged(StagedResult {
+                situation_title: shown.situation_title,
+                answer: String::new(),
+                counts: shown.counts,
+                version: shown.version,
+                ranking: shown.ranking,
+            })
+        } else {
+            PublicView::Shown(shown)
+        };
+        Some(PublicResult {
+            result,
+            reveal: status,
+        })
+    }
+
+    /// Шкалы для игроков: эффект ответа виден вместе с ответом, не раньше.
+    fn visible_meters(&self) -> (Vec<MeterView>, Option<EndingView>) {
+        if self.reveal_status().stage < reveal::Stage::Answer {
+            let before = self.meters.before_round();
+            (before.view(), before.ending())
+        } else {
+            (self.meters.view(), self.meters.ending())
+        }
+    }
+
+    fn tally(&self) -> Option<Tally> {
+        self.voting
+            .is_ranked()
+            .then(|| self.ballots.tally(self.voting))
+    }
+
+    /// То, по чему выбирается пара: клики или очки ранжированного голосования.
+    fn vote_counts(&self) -> [u64; 3] {
+        self.tally().map_or(self.counts, |t| t.scores)
+    }
+
+    /// Стратегия выбора пары: у ситуации своя, иначе комнатная.
+    fn winner_rule(&self) -> WinnerRule {
+        self.situations
+            .get(self.current_index)
+            .and_then(|s| s.winner)
+            .unwrap_or(self.winner)
+    }
+
+    /// Пара, от которой зависит ветка: показанная, а до показа — ожидаемая.
+    fn deciding_pair(&self) -> (Reaction, Reaction) {
+        self.last_result
+            .as_ref()
+            .and_then(|r| r.pair)
+            .unwrap_or_else(|| {
+                let (a, b) = self.winner_rule().strategy().expected(&self.vote_counts());
+                ordered_tuple(a, b)
+            })
+    }
+
+    fn situation(&self) -> Result<&Situation, ApiError> {
+        self.situations
+            .get(self.current_index)
+            .ok_or(ApiError::NoSituation)
+    }
 }
 
 type Shared = Arc<Mutex<AppState>>;
 
+// Паника в одном хендлере не должна блокировать игру до перезапуска:
+// отравленный мьютекс забираем как есть. Многошаговые хендлеры (show, next,
+// reset) сначала всё проверяют и считают без записи (plan/draft), а связанные
+// записи делают подряд, без unwrap и падающей индексации, — паники между ними
+// не ждём. Если она всё же случится, рассинхронится только текущая ситуация,
+// и «Сбросить» вернёт её к началу голосования.
+fn lock(state: &Shared) -> MutexGuard<'_, AppState> {
+    state.lock().unwrap_or_else(|poisoned: PoisonError<_>| {
+        warn!("state mutex was poisoned by a panicked handler, recovering");
+        state.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+// ===================== Ошибки API =====================
+
+enum ApiError {
+    NoSituation,
+    BadRequest(String),
+    NotFound(String),
+    Internal(String),
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    ok: bool,
+    error: String,
+}
+
+fn error_json(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { ok: false, error })).into_response()
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::NoSituation => error_json(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Нет ни одной ситуации".to_string(),
+            ),
+            ApiError::BadRequest(msg) => error_json(StatusCode::BAD_REQUEST, msg),
+            ApiError::NotFound(msg) => error_json(StatusCode::NOT_FOUND, msg),
+            ApiError::Internal(msg) => error_json(StatusCode::INTERNAL_SERVER_ERROR, msg),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(r: JsonRejection) -> Self {
+        ApiError::BadRequest(r.body_text())
+    }
+}
+
+// вместо оборванного соединения отдаём JSON с 500
+fn panic_to_json(err: Box<dyn Any + Send + 'static>) -> Response {
+    let detail = err
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| err.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_default();
+    error!(panic = %detail, "handler panicked");
+    error_json(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Внутренняя ошибка сервера".to_string(),
+    )
+}
+
+// комната пока одна, но в логах её уже помечаем
+const ROOM: &str = "main";
+
+// сколько строк таблицы лидеров показываем после ответа
+const LEADERBOARD_SIZE: usize = 10;
+
+// показываем, если у ситуации нет заготовленных ответов (вопрос из зала)
+const FREE_DISCUSSION_ANSWER: &str = "Заготовленного ответа нет — обсуждаем всем залом!";
+
+// сколько после сигнала продолжаем отвечать, чтобы игроки успели увидеть объявление
+// (клиенты опрашивают сервер раз в 1.5 секунды)
+const SHUTDOWN_NOTICE_GRACE: Duration = Duration::from_secs(3);
+
 // ===================== Entry =====================
 
 #[tokio::main]
 async fn main() {
+    init_tracing();
+
     let situations = build_situations();
-    let state = Arc::new(Mutex::new(AppState {
-        situations,
-        current_index: 0,
-        counts: [0, 0, 0],
-        last_result: None,
-        result_version: 0,
-    }));
-
-    let app = Router::new()
+    let meter_config = build_meters();
+    if let Err(e) =
+        validate_deck(&situations).and_then(|_| meters::validate(&meter_config, &situations))
+    {
+        error!(error = %e, "invalid deck");
+        std::process::exit(1);
+    }
+    let mut initial = AppState::new(situations);
+    initial.meters = Meters::new(meter_config);
+    initial.room_code = join::room_code_from_env();
+    initial.public_url = std::env::var("PUBLIC_URL").ok();
+    initial.prediction_mode = std::env::var("PREDICTION_MODE").is_ok_and(|v| v == "1");
+    // TEAMS="Разработка,Менеджмент" — включает командный режим
+    if let Ok(v) = std::env::var("TEAMS") {
+        initial.set_teams(parse_team_names(v.split(',')));
+    }
+    // WINNER: top-two (по умолчанию) | weighted-random | least-popular
+    if let Ok(v) = std::env::var("WINNER") {
+        match WinnerRule::from_str(&v) {
+            Some(rule) => initial.winner = rule,
+            None => warn!(value = %v, "unknown WINNER, keeping top-two"),
+        }
+    }
+    // VOTING: clicks (по умолчанию) | borda | pairwise
+    if let Ok(v) = std::env::var("VOTING") {
+        match VotingMode::from_str(&v) {
+            Some(mode) => initial.voting = mode,
+            None => warn!(value = %v, "unknown VOTING, keeping clicks"),
+        }
+    }
+    // REVEAL_STAGES="bars=2,countdown=3" (секунды) | off — по умолчанию ответ сразу
+    if let Ok(v) = std::env::var("REVEAL_STAGES") {
+        match reveal::Config::from_str(&v) {
+            Ok(config) => initial.reveal_config = config,
+            Err(e) => warn!(value = %v, error = %e, "bad REVEAL_STAGES, showing answers at once"),
+        }
+    }
+    // DISCUSSION_SECS=180 — обсуждение стартует само после каждого показа
+    if let Ok(v) = std::env::var("DISCUSSION_SECS") {
+        match v.parse::<u64>() {
+            Ok(secs) if (1..=MAX_DISCUSSION_SECS).contains(&secs) => {
+                initial.discussion_secs = Some(secs)
+            }
+            _ => warn!(value = %v, "bad DISCUSSION_SECS, discussion starts by hand"),
+        }
+    }
+    // TRACKER_CONFIG=tracker.json — шаблон запроса для выгрузки договорённостей
+    if let Some(path) = std::env::var_os("TRACKER_CONFIG") {
+        let path = std::path::PathBuf::from(path);
+        match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| tracker::Config::from_json(&json))
+        {
+            Ok(config) => initial.tracker = Some(Arc::new(config)),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "bad TRACKER_CONFIG, tracker export disabled")
+            }
+        }
+    }
+    // ANSWER_VARIANTS: random | round-robin | lru (по умолчанию)
+    if let Ok(v) = std::env::var("ANSWER_VARIANTS") {
+        match VariantStrategy::from_str(&v) {
+            Some(strategy) => initial.variants.strategy = strategy,
+            None => warn!(value = %v, "unknown ANSWER_VARIANTS, keeping lru"),
+        }
+    }
+    let state_path = persist::state_path();
+    match persist::load(&mut initial, &state_path) {
+        Ok(true) => {
+            info!(path = %state_path.display(), situation = initial.current_index, "state restored")
+        }
+        Ok(false) => {}
+        Err(e) => warn!(path = %state_path.display(), error = %e, "failed to restore state"),
+    }
+    let initial_code = initial.room_code.clone();
+    let state = Arc::new(Mutex::new(initial));
+    let app = build_app(state.clone());
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
+    let listener = TcpListener::bind(addr).await.unwrap();
+    info!(%addr, room_code = initial_code.as_deref().unwrap_or("-"), "listening");
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(state.clone()))
+    .await
+    .unwrap();
+
+    // запросы дослужены — сохраняем прогресс
+    let st = lock(&state);
+    match persist::save(&st, &state_path) {
+        Ok(()) => info!(path = %state_path.display(), "state saved"),
+        Err(e) => error!(path = %state_path.display(), error = %e, "failed to save state"),
+    }
+}
+
+/// Собирает роутер со всеми маршрутами и слоями — и для main, и для тестов.
+fn build_app(state: Shared) -> Router {
+    Router::new()
         .route("/", get(index_page))
         .route("/admin", get(admin_page))
+        .route("/present", get(present_page))
+        .route("/join", get(join_page))
+        .route("/r/:code", get(short_link))
+        .route("/qr.svg", get(get_qr_svg))
+        .route("/qr.png", get(get_qr_png))
+        .route("/api/join", get(get_join_info))
         .route("/api/current", get(get_current_situation))
         .route("/api/click", post(post_click))
+        .route("/api/rank", post(post_ranking))
         .route("/api/result", get(get_result_for_players))
-        .route("/admin/show", get(admin_show))
+        .route("/api/present", get(get_present_state))
+        .route("/admin/show", get(admin_show).post(admin_show))
+        .route("/admin/preview", get(admin_preview).post(admin_preview))
+        .route("/admin/reveal-all", post(admin_reveal_all))
+        .route("/admin/reveal/skip", post(admin_reveal_skip))
         .route("/admin/next", post(admin_next))
+        .route("/admin/upcoming", get(admin_upcoming))
+        .route("/admin/script", get(admin_script))
         .route("/admin/reset", post(admin_reset))
-        .with_state(state);
+        .route("/admin/discussion", post(admin_start_discussion))
+        .route("/admin/discussion/stop", post(admin_stop_discussion))
+        .route("/admin/notes", get(admin_list_notes).post(admin_add_note))
+        .route("/admin/notes/:id", delete(admin_delete_note))
+        .route("/admin/export", get(admin_export))
+        .route("/admin/tracker", get(admin_tracker_status))
+        .route("/admin/tracker/push", post(admin_tracker_push))
+        .route("/admin/restart", post(admin_restart))
+        .route("/api/players", post(post_player))
+        .route("/api/players/:id", get(get_player))
+        .route("/api/predict", post(post_prediction))
+        .route("/api/leaderboard", get(get_leaderboard))
+        .route("/admin/prediction", post(admin_set_prediction_mode))
+        .route("/admin/teams", post(admin_set_teams))
+        .route("/admin/voting", post(admin_set_voting))
+        .route("/admin/winner", post(admin_set_winner))
+        .route("/api/emoji", get(get_emoji).post(post_emoji))
+        .route("/api/questions", post(post_question))
+        .route("/admin/questions", get(admin_list_questions))
+        .route("/admin/questions/:id", post(admin_moderate_question))
+        .route("/admin/questions/:id/promote", post(admin_promote_question))
+        .route("/metrics", get(get_metrics))
+        .route_layer(middleware::from_fn_with_state(state.clone(), track_latency))
+        .layer(CatchPanicLayer::custom(panic_to_json))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .with_state(state)
+}
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    let listener = TcpListener::bind(addr).await.unwrap();
-    println!("Listening on http://{addr}");
+// ждём Ctrl+C / SIGTERM, предупреждаем игроков и только потом отпускаем сервер
+async fn shutdown_signal(state: Shared) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("shutdown requested, notifying players");
+    lock(&state).notice =
+        Some("Сервер перезапускается, прогресс сохранён. Скоро вернёмся!".to_string());
+    tokio::time::sleep(SHUTDOWN_NOTICE_GRACE).await;
+}
 
-    axum::serve(listener, app).await.unwrap();
+// RUST_LOG — фильтр (по умолчанию info), LOG_FORMAT=json — JSON-логи вместо текста
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    if std::env::var("LOG_FORMAT").is_ok_and(|v| v.eq_ignore_ascii_case("json")) {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
 }
 
 // ===================== Handlers =====================
@@ -101,24 +739,100 @@ async fn admin_page() -> Html<&'static str> {
     Html(ADMIN_HTML)
 }
 
+async fn present_page() -> Html<&'static str> {
+    Html(PRESENT_HTML)
+}
+
+async fn join_page() -> Html<&'static str> {
+    Html(JOIN_HTML)
+}
+
+// короткая ссылка /r/КОД: верный код ведёт в игру, неверный — обратно на ввод
+async fn short_link(State(state): State<Shared>, Path(code): Path<String>) -> Redirect {
+    let st = lock(&state);
+    match &st.room_code {
+        Some(expected) if *expected == join::normalize_code(&code) => Redirect::to("/"),
+        Some(_) => Redirect::to("/join?bad=1"),
+        None => Redirect::to("/"),
+    }
+}
+
+#[derive(Serialize)]
+struct JoinResponse {
+    url: String,
+    code: Option<String>,
+}
+
+fn join_info(state: &Shared, headers: &HeaderMap) -> JoinResponse {
+    let st = lock(state);
+    let base = join::base_url(st.public_url.as_deref(), headers);
+    JoinResponse {
+        url: join::player_url(&base, st.room_code.as_deref()),
+        code: st.room_code.clone(),
+    }
+}
+
+async fn get_join_info(State(state): State<Shared>, headers: HeaderMap) -> Json<JoinResponse> {
+    Json(join_info(&state, &headers))
+}
+
+async fn get_qr_svg(State(state): State<Shared>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let url = join_info(&state, &headers).url;
+    let svg = join::qr_svg(&url).map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+async fn get_qr_png(State(state): State<Shared>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let url = join_info(&state, &headers).url;
+    let png = join::qr_png(&url).map_err(ApiError::Internal)?;
+    Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
 #[derive(Serialize)]
 struct CurrentSituationResponse {
     title: String,
     description: String,
+    notice: Option<String>,
+    prediction_mode: bool,
+    teams: Vec<String>,
+    meters: Vec<MeterView>,
+    ending: Option<EndingView>,
+    voting: VotingMode,
+    winner: WinnerRule,
+    discussion: Option<discussion::Status>,
 }
 
-async fn get_current_situation(State(state): State<Shared>) -> Json<CurrentSituationResponse> {
-    let st = state.lock().unwrap();
-    let s = &st.situations[st.current_index];
-    Json(CurrentSituationResponse {
+async fn get_current_situation(
+    State(state): State<Shared>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+) -> Result<Json<CurrentSituationResponse>, ApiError> {
+    let mut st = lock(&state);
+    // игроки опрашивают этот эндпоинт постоянно — по нему и считаем подключённых
+    if let Some(ConnectInfo(addr)) = peer {
+        st.metrics.player_seen(addr.ip());
+    }
+    let s = st.situation()?;
+    let (meters, ending) = st.visible_meters();
+    Ok(Json(CurrentSituationResponse {
         title: s.title.clone(),
-        description: s.description.clone(),
-    })
+        description: template::render(&s.description, &st.template_vars()),
+        notice: st.notice.clone(),
+        prediction_mode: st.prediction_mode,
+        teams: st.teams.clone(),
+        meters,
+        ending,
+        voting: st.voting,
+        winner: st.winner,
+        discussion: st.discussion_status(),
+    }))
 }
 
 #[derive(Deserialize)]
 struct ClickRequest {
     reaction: String,
+    // токен игрока, если он представился (нужен для командного счёта)
+    #[serde(default)]
+    player: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -128,93 +842,1327 @@ struct ClickResponse {
 
 async fn post_click(
     State(state): State<Shared>,
-    Json(payload): Json<ClickRequest>,
-) -> Json<ClickResponse> {
-    let mut st = state.lock().unwrap();
+    payload: Result<Json<ClickRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
     if let Some(r) = Reaction::from_str(&payload.reaction) {
-        match r {
-            Reaction::Lie => st.counts[0] += 1,
-            Reaction::Delay => st.counts[1] += 1,
-            Reaction::Freeze => st.counts[2] += 1,
+        let idx = match r {
+            Reaction::Lie => 0,
+            Reaction::Delay => 1,
+            Reaction::Freeze => 2,
+        };
+        st.counts[idx] += 1;
+        st.metrics.click(idx);
+        let team = payload
+            .player
+            .as_deref()
+            .and_then(|id| st.players.get(id))
+            .and_then(|p| p.team);
+        if let Some(c) = team.and_then(|t| st.team_counts.get_mut(t)) {
+            c[idx] += 1;
         }
+    } else {
+        st.metrics.click_rejected();
     }
-    Json(ClickResponse { ok: true })
+    Ok(Json(ClickResponse { ok: true }))
 }
 
-// Админ нажал “Показать ответ”
-async fn admin_show(State(state): State<Shared>) -> Json<ShownResult> {
-    let mut st = state.lock().unwrap();
+// ===================== Ранжированное голосование =====================
 
-    // сначала забираем всё неизменяемое
-    let situation = &st.situations[st.current_index];
-    let (r1, r2) = top_two(&st.counts);
-    let key = ordered_tuple(r1, r2);
-    let answer = situation
-        .answers
-        .get(&key)
-        .cloned()
-        .unwrap_or_else(|| "Ответ не найден для этой комбинации".to_string());
-    let situation_title = situation.title.clone();
-    let counts = st.counts;
+#[derive(Deserialize)]
+struct RankRequest {
+    // от лучшей реакции к худшей: все три или две верхние
+    ranking: Vec<Reaction>,
+    #[serde(default)]
+    player: Option<String>,
+}
+
+async fn post_ranking(
+    State(state): State<Shared>,
+    payload: Result<Json<RankRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    if !st.voting.is_ranked() {
+        return Err(ApiError::BadRequest(
+            "Сейчас голосуем кликами, а не рейтингом".to_string(),
+        ));
+    }
+    // переголосовать можно только представившись, анонимные бюллетени копятся
+    let player = payload.player.filter(|id| st.players.get(id).is_some());
+    st.ballots
+        .cast(&payload.ranking, player.as_deref())
+        .map_err(ApiError::BadRequest)?;
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+#[derive(Deserialize)]
+struct VotingRequest {
+    mode: VotingMode,
+}
+
+async fn admin_set_voting(
+    State(state): State<Shared>,
+    payload: Result<Json<VotingRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    st.voting = payload.mode;
+    info!(room = ROOM, mode = ?payload.mode, "voting mode");
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+// ===================== Стратегия выбора пары =====================
+
+async fn admin_set_winner(
+    State(state): State<Shared>,
+    payload: Result<Json<WinnerRule>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(rule) = payload?;
+    if let WinnerRule::Override { pair: (a, b) } = rule {
+        if a == b {
+            return Err(ApiError::BadRequest(
+                "В паре нужны две разные реакции".to_string(),
+            ));
+        }
+    }
+    let mut st = lock(&state);
+    st.winner = rule;
+    info!(room = ROOM, rule = ?rule, "winner strategy");
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+// правка ведущего перед показом; всё необязательное
+#[derive(Deserialize, Default)]
+struct ShowOverride {
+    // показать эту пару, как бы ни голосовали
+    #[serde(default)]
+    pair: Option<(Reaction, Reaction)>,
+    // конкретный вариант из пула пары
+    #[serde(default)]
+    variant: Option<usize>,
+    // свой текст вместо заготовки (шаблоны работают)
+    #[serde(default)]
+    answer: Option<String>,
+}
+
+// ограничение на ответ ведущего, как у вопросов из зала
+const MAX_ADHOC_ANSWER_LEN: usize = 1000;
+
+impl ShowOverride {
+    /// Пустое тело — без правки. Текст ответа обрезается и проверяется как шаблон.
+    fn parse(body: &[u8]) -> Result<Self, ApiError> {
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(ShowOverride::default());
+        }
+        let mut ov: ShowOverride = serde_json::from_slice(body)
+            .map_err(|e| ApiError::BadRequest(format!("Некорректный JSON: {e}")))?;
+        if let Some((a, b)) = ov.pair {
+            if a == b {
+                return Err(ApiError::BadRequest(
+                    "В паре нужны две разные реакции".to_string(),
+                ));
+            }
+        }
+        if let Some(raw) = ov.answer.take() {
+            let text = raw.trim();
+            if text.is_empty() {
+                return Err(ApiError::BadRequest("Пустой ответ".to_string()));
+            }
+            if text.chars().count() > MAX_ADHOC_ANSWER_LEN {
+                return Err(ApiError::BadRequest(format!(
+                    "Ответ длиннее {MAX_ADHOC_ANSWER_LEN} символов"
+                )));
+            }
+            template::validate(text).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+            ov.answer = Some(text.to_string());
+        }
+        Ok(ov)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pair.is_none() && self.variant.is_none() && self.answer.is_none()
+    }
+}
+
+/// Какую пару покажем и откуда возьмём ответ — до выбора варианта.
+struct Plan {
+    counts: [u64; 3],
+    ranking: Option<Tally>,
+    selection: WinnerRule,
+    key: (Reaction, Reaction),
+    rule: Option<String>,
+    // сколько вариантов у пары; 0 — ответ по правилу или свой текст
+    pool_len: usize,
+    overridden: bool,
+    // для черновика: случайные стратегии не бросают кубик, а берут ожидаемую пару
+    preview: bool,
+}
+
+/// Готовый ответ; состояние для него не менялось.
+struct Draft {
+    plan: Plan,
+    variant: usize,
+    answer: String,
+    teams: Option<Vec<TeamResult>>,
+    effects: Vec<(String, i64)>,
+}
+
+impl AppState {
+    fn plan(&self, ov: &ShowOverride, preview: bool) -> Result<Plan, ApiError> {
+        let situation = self.situation()?;
+        let ranking = self.tally();
+        let counts = ranking.as_ref().map_or(self.counts, |t| t.scores);
+        let selection = self.winner_rule();
+        let (r1, r2) = ov.pair.unwrap_or_else(|| {
+            let strategy = selection.strategy();
+            if preview {
+                strategy.expected(&counts)
+            } else {
+                strategy.select(&counts)
+            }
+        });
+        let key = ordered_tuple(r1, r2);
+        let overridden = !ov.is_empty();
+        // правка ведущего важнее правил колоды
+        let rule = if overridden {
+            None
+        } else {
+            situation
+                .matched_rule(&self.template_vars_for(counts))
+                .map(|r| r.when.clone())
+        };
+        // ответ по правилу или свой текст — один, варианты пула не тратим
+        let pool_len = match (&rule, &ov.answer) {
+            (None, None) => situation.answers.get(&key).map_or(0, Vec::len),
+            _ => 0,
+        };
+        if let Some(v) = ov.variant {
+            if v >= pool_len.max(1) {
+                return Err(ApiError::BadRequest(format!(
+                    "У пары {key:?} нет варианта {v}, всего вариантов: {pool_len}"
+                )));
+            }
+        }
+        Ok(Plan {
+            counts,
+            ranking,
+            selection,
+            key,
+            rule,
+            pool_len,
+            overridden,
+            preview,
+        })
+    }
+
+    fn draft(&self, plan: Plan, variant: usize, adhoc: Option<&str>) -> Result<Draft, ApiError> {
+        let situation = self.situation()?;
+        let vars = self.template_vars_for(plan.counts);
+        let answer = match adhoc {
+            Some(text) => template::render(text, &vars),
+            None if plan.overridden => situation.pair_answer(plan.key, variant, &vars),
+            None => situation.answer_for(plan.key, variant, &vars),
+        };
+        let strategy = plan.selection.strategy();
+        let teams = if !self.team_results() {
+            None
+        } else {
+            Some(
+                self.teams
+                    .iter()
+                    .zip(&self.team_counts)
+                    .map(|(name, &tc)| {
+                        let (a, b) = if plan.preview {
+                            strategy.expected(&tc)
+                        } else {
+                            strategy.select(&tc)
+                        };
+                        let pair = ordered_tuple(a, b);
+                        TeamResult {
+                            name: name.clone(),
+                            counts: tc,
+                            pair,
+                            answer: situation.answer_for(
+                                pair,
+                                variant,
+                                &self.template_vars_for(tc),
+                            ),
+                        }
+                    })
+                    .collect(),
+            )
+        };
+        let effects = situation
+            .effects
+            .get(&plan.key)
+            .cloned()
+            .unwrap_or_default();
+        Ok(Draft {
+            plan,
+            variant,
+            answer,
+            teams,
+            effects,
+        })
+    }
+}
+
+// Админ нажал “Показать ответ”. GET — как решил зал, POST с телом — с правкой ведущего.
+async fn admin_show(
+    State(state): State<Shared>,
+    body: Bytes,
+) -> Result<Json<ShownResult>, ApiError> {
+    let ov = ShowOverride::parse(&body)?;
+    let mut st = lock(&state);
+
+    let plan = st.plan(&ov, false)?;
+    let current = st.current_index;
+    let variant = match ov.variant {
+        Some(v) => v,
+        None => st.variants.pick(current, plan.key, plan.pool_len),
+    };
+    let Draft {
+        plan,
+        variant,
+        answer,
+        teams,
+        effects,
+    } = st.draft(plan, variant, ov.answer.as_deref())?;
+    let Plan {
+        counts,
+        ranking,
+        selection,
+        key,
+        rule,
+        pool_len,
+        overridden,
+        ..
+    } = plan;
 
-    // теперь можно мутировать состояние
+    // дальше — всё, что меняет состояние
+    let situation_title = st.situation()?.title.clone();
+    let leaderboard = if st.prediction_mode {
+        // лидера угадывают по голосам, даже если пару выбрали иначе;
+        // навязанная пара — не выбор зала, тогда и пару сверяем с голосами
+        let (first, second) = top_two(&counts);
+        let forced = ov.pair.is_some() || matches!(selection, WinnerRule::Override { .. });
+        let voted = if forced {
+            ordered_tuple(first, second)
+        } else {
+            key
+        };
+        let lucky = st.players.score(voted, first);
+        info!(
+            room = ROOM,
+            situation = current,
+            lucky,
+            "predictions scored"
+        );
+        Some(st.players.leaderboard(LEADERBOARD_SIZE))
+    } else {
+        None
+    };
+    let changes = st.meters.apply(&effects);
+    let ending = st.meters.ending();
     st.result_version += 1;
     let shown = ShownResult {
         situation_title,
         answer,
+        variant,
+        variants: pool_len,
         counts,
         version: st.result_version,
+        pair: Some(key),
+        selection,
+        rule,
+        overridden,
+        comparison: None,
+        leaderboard,
+        teams,
+        changes,
+        ending,
+        ranking,
     };
     st.last_result = Some(shown.clone());
+    st.reveal = Some(Reveal::start(Instant::now()));
+    st.discussion = st
+        .discussion_secs
+        .map(|secs| Discussion::start(Instant::now(), secs));
+    let index = st.current_index;
+    st.history.current(index, &shown.situation_title).shown = Some(history::Shown {
+        answer: shown.answer.clone(),
+        pair: shown.pair,
+        counts,
+        overridden,
+    });
+    st.metrics.reveal();
+    info!(
+        room = ROOM,
+        situation = st.current_index,
+        version = shown.version,
+        lie = counts[0],
+        delay = counts[1],
+        freeze = counts[2],
+        pair = ?key,
+        variant,
+        voting = ?st.voting,
+        selection = ?selection,
+        rule = shown.rule.as_deref().unwrap_or("-"),
+        overridden,
+        adhoc = ov.answer.is_some(),
+        ended = shown.ending.is_some(),
+        "admin show"
+    );
 
-    Json(shown)
+    Ok(Json(shown))
 }
 
-// игроки опрашивают результат
-async fn get_result_for_players(State(state): State<Shared>) -> Json<Option<ShownResult>> {
-    let st = state.lock().unwrap();
-    Json(st.last_result.clone())
+#[derive(Serialize)]
+struct PreviewResponse {
+    answer: String,
+    variant: usize,
+    variants: usize,
+    counts: [u64; 3],
+    pair: (Reaction, Reaction),
+    selection: WinnerRule,
+    rule: Option<String>,
+    overridden: bool,
+    teams: Option<Vec<TeamResult>>,
+    // как сдвинулись бы шкалы и не закончилась бы на этом игра
+    changes: Vec<Change>,
+    ending: Option<EndingView>,
+    // уже показанный игрокам ответ, если есть — для сравнения
+    shown_version: Option<u64>,
+    // false — при показе пару или вариант выберет случай, черновик лишь ожидаемый
+    binding: bool,
 }
 
-// админ -> следующая ситуация
-async fn admin_next(State(state): State<Shared>) -> Json<ClickResponse> {
-    let mut st = state.lock().unwrap();
-    st.current_index = (st.current_index + 1) % st.situations.len();
-    st.counts = [0, 0, 0];
-    st.last_result = None;
-    Json(ClickResponse { ok: true })
-}
+// Черновик ответа только для админа: то же, что сделал бы show, но без публикации.
+// Ничего не меняет — ни версию, ни историю вариантов, ни шкалы, ни очки. Где show бросает
+// кубик, черновик берёт ожидаемый исход и помечается как необязывающий.
+async fn admin_preview(
+    State(state): State<Shared>,
+    body: Bytes,
+) -> Result<Json<PreviewResponse>, ApiError> {
+    let ov = ShowOverride::parse(&body)?;
+    let st = lock(&state);
 
-// админ -> сброс
-async fn admin_reset(State(state): State<Shared>) -> Json<ClickResponse> {
-    let mut st = state.lock().unwrap();
-    st.counts = [0, 0, 0];
-    st.last_result = None;
-    Json(ClickResponse { ok: true })
+    let plan = st.plan(&ov, true)?;
+    let random_pair = matches!(plan.selection, WinnerRule::WeightedRandom)
+        && (ov.pair.is_none() || st.team_results());
+    let random_variant = ov.variant.is_none()
+        && plan.pool_len > 1
+        && st.variants.strategy == VariantStrategy::Random;
+    let variant = match ov.variant {
+        Some(v) => v,
+        None => st.variants.peek(st.current_index, plan.key, plan.pool_len),
+    };
+    let draft = st.draft(plan, variant, ov.answer.as_deref())?;
+    let mut meters = st.meters.clone();
+    let changes = meters.apply(&draft.effects);
+    Ok(Json(PreviewResponse {
+        answer: draft.answer,
+        variant: draft.variant,
+        variants: draft.plan.pool_len,
+        counts: draft.plan.counts,
+        pair: draft.plan.key,
+        selection: draft.plan.selection,
+        rule: draft.plan.rule,
+        overridden: draft.plan.overridden,
+        teams: draft.teams,
+        changes,
+        ending: meters.ending(),
+        shown_version: st.last_result.as_ref().map(|r| r.version),
+        binding: !random_pair && !random_variant,
+    }))
 }
 
-// ===================== Утилиты =====================
+// после показа: раскрыть ответы на все пары с долями голосов
+async fn admin_reveal_all(State(state): State<Shared>) -> Result<Json<Comparison>, ApiError> {
+    let mut st = lock(&state);
+    let Some(shown) = st.last_result.as_ref() else {
+        return Err(ApiError::BadRequest("Сначала покажите ответ".to_string()));
+    };
+    let situation = st.situation()?;
+    if situation.answers.is_empty() {
+        return Err(ApiError::BadRequest(
+            "У ситуации нет заготовленных ответов".to_string(),
+        ));
+    }
+    // доли — по голосам на момент показа, а не по тем, что докликали после
+    let counts = shown.counts;
+    let chosen = shown.pair;
+    let total: u64 = counts.iter().sum();
+    let vars = st.template_vars_for(counts);
+    let mut entries: Vec<ComparisonEntry> = situation
+        .answers
+        .iter()
+        .map(|(&pair, pool)| {
+            let votes = counts[pair.0 as usize] + counts[pair.1 as usize];
+            ComparisonEntry {
+                pair,
+                answers: pool.iter().map(|a| template::render(a, &vars)).collect(),
+                votes,
+                share: (votes * 100 + total / 2).checked_div(total).unwrap_or(0),
+                chosen: Some(pair) == chosen,
+            }
+        })
+        .collect();
+    entries.sort_by_key(|e| (std::cmp::Reverse(e.votes), e.pair.0 as u8, e.pair.1 as u8));
 
-fn idx_to_reaction(i: usize) -> Reaction {
-    match i {
-        0 => Reaction::Lie,
-        1 => Reaction::Delay,
-        _ => Reaction::Freeze,
+    st.result_version += 1;
+    let comparison = Comparison {
+        version: st.result_version,
+        entries,
+    };
+    if let Some(shown) = st.last_result.as_mut() {
+        shown.comparison = Some(comparison.clone());
     }
+    info!(
+        room = ROOM,
+        situation = st.current_index,
+        version = comparison.version,
+        "admin reveal all"
+    );
+    Ok(Json(comparison))
 }
 
-fn ordered_tuple(a: Reaction, b: Reaction) -> (Reaction, Reaction) {
-    if (a as u8) <= (b as u8) {
-        (a, b)
-    } else {
-        (b, a)
+// админ -> следующий этап показа (полоски -> отсчёт -> ответ)
+async fn admin_reveal_skip(State(state): State<Shared>) -> Result<Json<reveal::Status>, ApiError> {
+    let mut st = lock(&state);
+    if st.last_result.is_none() {
+        return Err(ApiError::BadRequest("Сначала покажите ответ".to_string()));
     }
+    let config = st.reveal_config;
+    let status = match st.reveal.as_mut() {
+        Some(r) => r.skip(&config, Instant::now()),
+        None => reveal::Status::DONE,
+    };
+    info!(room = ROOM, situation = st.current_index, stage = ?status.stage, "admin reveal skip");
+    Ok(Json(status))
 }
 
-fn top_two(counts: &[u64; 3]) -> (Reaction, Reaction) {
-    let mut pairs = vec![(counts[0], 0usize), (counts[1], 1usize), (counts[2], 2usize)];
-    pairs.sort_by(|a, b| b.0.cmp(&a.0));
-    (idx_to_reaction(pairs[0].1), idx_to_reaction(pairs[1].1))
+// игроки опрашивают результат
+async fn get_result_for_players(State(state): State<Shared>) -> Json<Option<PublicResult>> {
+    let st = lock(&state);
+    Json(st.public_result())
+}
+
+#[derive(Serialize)]
+struct PresentResponse {
+    title: String,
+    description: String,
+    players: usize,
+    clicks: u64,
+    ballots: usize,
+    voting: VotingMode,
+    result: Option<PublicResult>,
+    meters: Vec<MeterView>,
+    ending: Option<EndingView>,
+    discussion: Option<discussion::Status>,
+    notice: Option<String>,
+}
+
+// экран проектора: всё, что нужно для показа, одним запросом
+async fn get_present_state(State(state): State<Shared>) -> Result<Json<PresentResponse>, ApiError> {
+    let st = lock(&state);
+    let s = st.situation()?;
+    let (meters, ending) = st.visible_meters();
+    Ok(Json(PresentResponse {
+        title: s.title.clone(),
+        description: template::render(&s.description, &st.template_vars()),
+        players: st.metrics.connected_players(),
+        clicks: st.counts.iter().sum(),
+        ballots: st.ballots.len(),
+        voting: st.voting,
+        result: st.public_result(),
+        meters,
+        ending,
+        discussion: st.discussion_status(),
+        notice: st.notice.clone(),
+    }))
+}
+
+// админ -> следующая ситуация
+async fn admin_next(State(state): State<Shared>) -> Result<Json<ClickResponse>, ApiError> {
+    let mut st = lock(&state);
+    if st.situations.is_empty() {
+        return Err(ApiError::NoSituation);
+    }
+    if let Some(ending) = st.meters.ending() {
+        return Err(ApiError::BadRequest(format!(
+            "Игра окончена: {}. Начните новую",
+            ending.title
+        )));
+    }
+    let prev_counts = st.counts; // клики уходящей ситуации — для лога
+    let pair = st.deciding_pair();
+    let (next, via_branch) = branching::next_index(&st.situations, st.current_index, pair);
+    st.end_discussion();
+    st.history.close();
+    st.current_index = next;
+    st.reset_counts();
+    st.last_result = None;
+    st.reveal = None;
+    st.players.new_round();
+    st.emoji.clear();
+    st.meters.new_round();
+    info!(
+        room = ROOM,
+        situation = st.current_index,
+        lie = prev_counts[0],
+        delay = prev_counts[1],
+        freeze = prev_counts[2],
+        via_branch,
+        "admin next"
+    );
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+#[derive(Serialize)]
+struct UpcomingResponse {
+    index: usize,
+    title: String,
+    pair: (Reaction, Reaction),
+    via_branch: bool,
+}
+
+// админ видит, куда поведёт «Дальше» при текущем раскладе голосов
+async fn admin_upcoming(State(state): State<Shared>) -> Result<Json<UpcomingResponse>, ApiError> {
+    let st = lock(&state);
+    st.situation()?;
+    let pair = st.deciding_pair();
+    let (index, via_branch) = branching::next_index(&st.situations, st.current_index, pair);
+    Ok(Json(UpcomingResponse {
+        index,
+        title: st.situations[index].title.clone(),
+        pair,
+        via_branch,
+    }))
+}
+
+#[derive(Serialize)]
+struct ScriptResponse {
+    index: usize,
+    total: usize,
+    title: String,
+    #[serde(flatten)]
+    script: Script,
+}
+
+// шпаргалка ведущего к текущей ситуации
+async fn admin_script(State(state): State<Shared>) -> Result<Json<ScriptResponse>, ApiError> {
+    let st = lock(&state);
+    let s = st.situation()?;
+    Ok(Json(ScriptResponse {
+        index: st.current_index,
+        total: st.situations.len(),
+        title: s.title.clone(),
+        script: s.script.clone(),
+    }))
+}
+
+// админ -> сброс
+async fn admin_reset(State(state): State<Shared>) -> Json<ClickResponse> {
+    let mut st = lock(&state);
+    info!(
+        room = ROOM,
+        situation = st.current_index,
+        lie = st.counts[0],
+        delay = st.counts[1],
+        freeze = st.counts[2],
+        "admin reset"
+    );
+    st.end_discussion();
+    // голосуют заново — показанный ответ в историю не идёт, заметки остаются
+    if let Some(entry) = st.history.open_entry_mut() {
+        entry.shown = None;
+    }
+    st.reset_counts();
+    st.last_result = None;
+    st.reveal = None;
+    st.meters.undo_round();
+    st.players.undo_round();
+    Json(ClickResponse { ok: true })
+}
+
+// админ -> новая игра: колода с начала, шкалы на старт (очки игроков остаются)
+async fn admin_restart(State(state): State<Shared>) -> Json<ClickResponse> {
+    let mut st = lock(&state);
+    info!(
+        room = ROOM,
+        situation = st.current_index,
+        ending = st.meters.ending().map(|e| e.title),
+        "admin restart"
+    );
+    st.end_discussion();
+    st.history.close();
+    st.current_index = 0;
+    st.reset_counts();
+    st.last_result = None;
+    st.reveal = None;
+    st.players.new_round();
+    st.emoji.clear();
+    st.meters.restart();
+    Json(ClickResponse { ok: true })
+}
+
+// ===================== Обсуждение и история =====================
+
+// если ни в запросе, ни в настройках комнаты длительности нет
+const DEFAULT_DISCUSSION_SECS: u64 = 180;
+const MAX_DISCUSSION_SECS: u64 = 3600;
+
+#[derive(Deserialize, Default)]
+struct DiscussionRequest {
+    #[serde(default)]
+    secs: Option<u64>,
+}
+
+// админ -> таймер обсуждения показанного ответа
+async fn admin_start_discussion(
+    State(state): State<Shared>,
+    body: Bytes,
+) -> Result<Json<discussion::Status>, ApiError> {
+    // пустое тело — длительность по умолчанию
+    let req: DiscussionRequest = if body.is_empty() {
+        DiscussionRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?
+    };
+    let mut st = lock(&state);
+    if st.last_result.is_none() {
+        return Err(ApiError::BadRequest("Сначала покажите ответ".to_string()));
+    }
+    let secs = req
+        .secs
+        .or(st.discussion_secs)
+        .unwrap_or(DEFAULT_DISCUSSION_SECS);
+    if !(1..=MAX_DISCUSSION_SECS).contains(&secs) {
+        return Err(ApiError::BadRequest(format!(
+            "Обсуждение — от 1 до {MAX_DISCUSSION_SECS} секунд"
+        )));
+    }
+    let now = Instant::now();
+    let discussion = Discussion::start(now, secs);
+    let status = discussion.status(st.answer_at(), now);
+    st.discussion = Some(discussion);
+    info!(
+        room = ROOM,
+        situation = st.current_index,
+        secs,
+        "discussion started"
+    );
+    Ok(Json(status))
+}
+
+// админ -> закончить обсуждение раньше
+async fn admin_stop_discussion(State(state): State<Shared>) -> Json<ClickResponse> {
+    let mut st = lock(&state);
+    st.end_discussion();
+    Json(ClickResponse { ok: true })
+}
+
+#[derive(Deserialize)]
+struct NoteRequest {
+    #[serde(default = "default_note_kind")]
+    kind: NoteKind,
+    text: String,
+}
+
+fn default_note_kind() -> NoteKind {
+    NoteKind::Note
+}
+
+// заметки и договорённости по текущей ситуации
+async fn admin_list_notes(State(state): State<Shared>) -> Json<Vec<Note>> {
+    let st = lock(&state);
+    let notes = st
+        .history
+        .open_entry()
+        .filter(|e| e.situation == st.current_index)
+        .map(|e| e.notes.clone())
+        .unwrap_or_default();
+    Json(notes)
+}
+
+async fn admin_add_note(
+    State(state): State<Shared>,
+    payload: Result<Json<NoteRequest>, JsonRejection>,
+) -> Result<Json<Note>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    let index = st.current_index;
+    let title = st.situation()?.title.clone();
+    let note = st
+        .history
+        .add_note(index, &title, payload.kind, &payload.text)
+        .map_err(ApiError::BadRequest)?;
+    info!(room = ROOM, situation = index, id = note.id, kind = ?note.kind, "note added");
+    Ok(Json(note))
+}
+
+async fn admin_delete_note(
+    State(state): State<Shared>,
+    Path(id): Path<u64>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let mut st = lock(&state);
+    if !st.history.remove_note(id) {
+        return Err(ApiError::NotFound(format!("Нет заметки #{id}")));
+    }
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: Option<String>,
+}
+
+// итоги сессии: JSON по умолчанию, ?format=markdown — текстом для вики
+async fn admin_export(
+    State(state): State<Shared>,
+    Query(q): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let st = lock(&state);
+    match q.format.as_deref() {
+        None | Some("json") => Ok(Json(st.history.entries().to_vec()).into_response()),
+        Some("markdown" | "md") => Ok((
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            st.history.to_markdown(),
+        )
+            .into_response()),
+        Some(other) => Err(ApiError::BadRequest(format!(
+            "Неизвестный формат {other}: json или markdown"
+        ))),
+    }
+}
+
+// ===================== Выгрузка в трекер =====================
+
+impl AppState {
+    /// Договорённости, которых ещё нет в трекере, и сколько уже заведено.
+    fn tracker_items(&self) -> (Vec<tracker::Item>, usize) {
+        let mut items = Vec::new();
+        let mut pushed = 0;
+        for (n, e) in self.history.entries().iter().enumerate() {
+            for note in e.notes.iter().filter(|n| n.kind == NoteKind::Action) {
+                if note.pushed {
+                    pushed += 1;
+                    continue;
+                }
+                items.push(tracker::Item {
+                    note_id: note.id,
+                    text: note.text.clone(),
+                    situation: e.title.clone(),
+                    number: n + 1,
+                    answer: e.shown.as_ref().map(|s| s.answer.clone()),
+                });
+            }
+        }
+        (items, pushed)
+    }
+}
+
+#[derive(Serialize)]
+struct TrackerStatus {
+    configured: bool,
+    busy: bool,
+    pending: usize,
+    pushed: usize,
+    report: Option<tracker::Report>,
+}
+
+async fn admin_tracker_status(State(state): State<Shared>) -> Json<TrackerStatus> {
+    let st = lock(&state);
+    let (items, pushed) = st.tracker_items();
+    Json(TrackerStatus {
+        configured: st.tracker.is_some(),
+        busy: st.tracker_busy,
+        pending: items.len(),
+        pushed,
+        report: st.tracker_report.clone(),
+    })
+}
+
+// админ -> завести договорённости в трекере; уже заведённые не повторяются
+async fn admin_tracker_push(
+    State(state): State<Shared>,
+) -> Result<Json<tracker::Report>, ApiError> {
+    let (config, items, skipped) = {
+        let mut st = lock(&state);
+        let Some(config) = st.tracker.clone() else {
+            return Err(ApiError::BadRequest(
+                "Трекер не настроен: задайте TRACKER_CONFIG".to_string(),
+            ));
+        };
+        if st.tracker_busy {
+            return Err(ApiError::BadRequest("Выгрузка уже идёт".to_string()));
+        }
+        st.tracker_busy = true;
+        let (items, skipped) = st.tracker_items();
+        (config, items, skipped)
+    };
+    // в отдельной задаче: если админ закроет вкладку, выгрузка всё равно
+    // доработает и снимет флаг, а отметки о заведённых задачах не потеряются
+    let shared = state.clone();
+    let task = tokio::spawn(async move {
+        let mut report = tracker::push(&config, &items).await;
+        report.skipped = skipped;
+        let mut st = lock(&state);
+        st.tracker_busy = false;
+        for d in report.items.iter().filter(|d| d.ok) {
+            st.history.mark_pushed(d.note_id);
+        }
+        info!(
+            room = ROOM,
+            delivered = report.delivered,
+            failed = report.failed,
+            skipped = report.skipped,
+            "tracker push"
+        );
+        st.tracker_report = Some(report.clone());
+        report
+    });
+    task.await.map(Json).map_err(|e| {
+        // задача упала, не дойдя до флага, — иначе выгрузку больше не запустить
+        lock(&shared).tracker_busy = false;
+        ApiError::Internal(e.to_string())
+    })
+}
+
+// ===================== Игроки и прогнозы =====================
+
+#[derive(Deserialize)]
+struct RegisterRequest {
+    name: String,
+    // индекс команды; не выбрал — распределим сами
+    #[serde(default)]
+    team: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct RegisterResponse {
+    ok: bool,
+    id: String,
+    name: String,
+    team: Option<usize>,
+}
+
+// игрок -> представился, получил токен
+async fn post_player(
+    State(state): State<Shared>,
+    payload: Result<Json<RegisterRequest>, JsonRejection>,
+) -> Result<Json<RegisterResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    let team_count = st.teams.len();
+    let team = match payload.team {
+        _ if team_count == 0 => None,
+        Some(t) if t < team_count => Some(t),
+        Some(_) => return Err(ApiError::BadRequest("Нет такой команды".to_string())),
+        None => Some(st.players.smallest_team(team_count)),
+    };
+    let id = st
+        .players
+        .register(&payload.name, team)
+        .map_err(ApiError::BadRequest)?;
+    let name = payload.name.trim().to_string();
+    info!(
+        room = ROOM,
+        players = st.players.len(),
+        team,
+        "player registered"
+    );
+    Ok(Json(RegisterResponse {
+        ok: true,
+        id,
+        name,
+        team,
+    }))
+}
+
+#[derive(Serialize)]
+struct PlayerResponse {
+    name: String,
+    points: u64,
+    prediction: Option<Prediction>,
+    team: Option<usize>,
+}
+
+async fn get_player(
+    State(state): State<Shared>,
+    Path(id): Path<String>,
+) -> Result<Json<PlayerResponse>, ApiError> {
+    let st = lock(&state);
+    let p = st
+        .players
+        .get(&id)
+        .ok_or_else(|| ApiError::NotFound("Неизвестный игрок".to_string()))?;
+    Ok(Json(PlayerResponse {
+        name: p.name.clone(),
+        points: p.points,
+        prediction: st.players.prediction(&id),
+        team: p.team,
+    }))
+}
+
+#[derive(Deserialize)]
+struct PredictRequest {
+    player: String,
+    #[serde(flatten)]
+    prediction: Prediction,
+}
+
+// игрок -> прогноз на текущую ситуацию (можно менять до показа ответа)
+async fn post_prediction(
+    State(state): State<Shared>,
+    payload: Result<Json<PredictRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    if !st.prediction_mode {
+        return Err(ApiError::BadRequest("Режим прогнозов выключен".to_string()));
+    }
+    st.players
+        .predict(&payload.player, payload.prediction)
+        .map_err(ApiError::BadRequest)?;
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+// полная таблица — для итогов сессии
+async fn get_leaderboard(State(state): State<Shared>) -> Json<Vec<LeaderboardEntry>> {
+    let st = lock(&state);
+    Json(st.players.leaderboard(st.players.len()))
+}
+
+#[derive(Deserialize)]
+struct ModeRequest {
+    enabled: bool,
+}
+
+async fn admin_set_prediction_mode(
+    State(state): State<Shared>,
+    payload: Result<Json<ModeRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    st.prediction_mode = payload.enabled;
+    info!(room = ROOM, enabled = payload.enabled, "prediction mode");
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+#[derive(Deserialize)]
+struct TeamsRequest {
+    teams: Vec<String>,
+}
+
+// админ -> задать команды (пустой список выключает режим); командный счёт обнуляется
+async fn admin_set_teams(
+    State(state): State<Shared>,
+    payload: Result<Json<TeamsRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let teams = parse_team_names(payload.teams.iter().map(String::as_str));
+    let mut st = lock(&state);
+    info!(room = ROOM, teams = ?teams, "teams set");
+    st.set_teams(teams);
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+fn parse_team_names<'a>(raw: impl Iterator<Item = &'a str>) -> Vec<String> {
+    raw.map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// ===================== Эмодзи-реакции =====================
+
+#[derive(Deserialize)]
+struct EmojiRequest {
+    emoji: Emoji,
+}
+
+// игрок -> реакция на показанный ответ; в counts не попадает
+async fn post_emoji(
+    State(state): State<Shared>,
+    payload: Result<Json<EmojiRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    if st.last_result.is_none() {
+        return Err(ApiError::BadRequest(
+            "Реагировать можно после показа ответа".to_string(),
+        ));
+    }
+    st.emoji.push(payload.emoji);
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+#[derive(Deserialize)]
+struct EmojiQuery {
+    since: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct EmojiResponse {
+    now: u64,
+    buckets: Vec<Bucket>,
+}
+
+// экран проектора опрашивает посекундные сводки
+async fn get_emoji(
+    State(state): State<Shared>,
+    Query(q): Query<EmojiQuery>,
+) -> Json<EmojiResponse> {
+    let st = lock(&state);
+    Json(EmojiResponse {
+        now: st.emoji.now(),
+        buckets: st.emoji.since(q.since),
+    })
+}
+
+// ===================== Вопросы из зала =====================
+
+#[derive(Deserialize)]
+struct QuestionRequest {
+    text: String,
+}
+
+// игрок -> анонимный вопрос в очередь модерации
+async fn post_question(
+    State(state): State<Shared>,
+    payload: Result<Json<QuestionRequest>, JsonRejection>,
+) -> Result<Json<ClickResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let mut st = lock(&state);
+    let id = st
+        .questions
+        .submit(&payload.text)
+        .map_err(ApiError::BadRequest)?;
+    info!(room = ROOM, question = id, "question submitted");
+    Ok(Json(ClickResponse { ok: true }))
+}
+
+async fn admin_list_questions(State(state): State<Shared>) -> Json<Vec<Question>> {
+    Json(lock(&state).questions.items.clone())
+}
+
+#[derive(Deserialize)]
+struct ModerateRequest {
+    status: Option<QuestionStatus>,
+    text: Option<String>,
+}
+
+// админ -> одобрить / отклонить / поправить текст
+async fn admin_moderate_question(
+    State(state): State<Shared>,
+    Path(id): Path<u64>,
+    payload: Result<Json<ModerateRequest>, JsonRejection>,
+) -> Result<Json<Question>, ApiError> {
+    let Json(payload) = payload?;
+    // сначала всё проверяем: отклонённый запрос не должен ничего менять
+    if payload.status == Some(QuestionStatus::Promoted) {
+        return Err(ApiError::BadRequest(
+            "Для превращения в ситуацию есть /promote".to_string(),
+        ));
+    }
+    let text = payload
+        .text
+        .map(|t| questions::clean(&t))
+        .transpose()
+        .map_err(ApiError::BadRequest)?;
+    let mut st = lock(&state);
+    let q = st
+        .questions
+        .get_mut(id)
+        .ok_or_else(|| ApiError::NotFound(format!("Нет вопроса #{id}")))?;
+    if let Some(text) = text {
+        q.text = text;
+    }
+    if let Some(status) = payload.status {
+        q.status = status;
+    }
+    let q = q.clone();
+    info!(room = ROOM, question = id, status = ?q.status, "question moderated");
+    Ok(Json(q))
+}
+
+#[derive(Deserialize, Default)]
+struct PromoteRequest {
+    description: Option<String>,
+    // без ответов — раунд свободного обсуждения
+    answers: Option<PromoteAnswers>,
+}
+
+#[derive(Deserialize)]
+struct PromoteAnswers {
+    lie_delay: String,
+    lie_freeze: String,
+    delay_freeze: String,
+}
+
+impl PromoteRequest {
+    // пустое тело — свободное обсуждение; битое или неполное — ошибка, а не тихий дефолт
+    fn parse(body: &[u8]) -> Result<Self, ApiError> {
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(PromoteRequest::default());
+        }
+        let mut req: PromoteRequest = serde_json::from_slice(body)
+            .map_err(|e| ApiError::BadRequest(format!("Некорректный JSON: {e}")))?;
+        if let Some(a) = &mut req.answers {
+            for text in [&mut a.lie_delay, &mut a.lie_freeze, &mut a.delay_freeze] {
+                *text = text.trim().to_string();
+                if text.is_empty() {
+                    return Err(ApiError::BadRequest(
+                        "Нужны ответы на все три пары".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(req)
+    }
+}
+
+/// Вопрос, ставший ситуацией. Колода живёт в коде, поэтому сохраняем саму вставку.
+#[derive(Clone, Serialize, Deserialize)]
+struct Promotion {
+    question: u64,
+    index: usize,
+    title: String,
+    description: String,
+    // врать+отложить, врать+заморозить, отложить+заморозить; нет — свободное обсуждение
+    answers: Option<[String; 3]>,
+}
+
+impl Promotion {
+    fn situation(&self) -> Situation {
+        Situation {
+            title: self.title.clone(),
+            description: self.description.clone(),
+            answers: match &self.answers {
+                Some([ld, lf, df]) => make_answers(ld, lf, df),
+                None => HashMap::new(),
+            },
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PromoteResponse {
+    ok: bool,
+    index: usize,
+}
+
+// админ -> одобренный вопрос становится следующей ситуацией сессии
+async fn admin_promote_question(
+    State(state): State<Shared>,
+    Path(id): Path<u64>,
+    body: Bytes,
+) -> Result<Json<PromoteResponse>, ApiError> {
+    let payload = PromoteRequest::parse(&body)?;
+    let mut st = lock(&state);
+    let q = st
+        .questions
+        .get_mut(id)
+        .ok_or_else(|| ApiError::NotFound(format!("Нет вопроса #{id}")))?;
+    if q.status != QuestionStatus::Approved {
+        return Err(ApiError::BadRequest("Сначала одобри вопрос".to_string()));
+    }
+
+    let title = q.text.clone();
+    // вставляем сразу за текущей, чтобы «Дальше» привело к ней
+    let index = (st.current_index + 1).min(st.situations.len());
+    let promotion = Promotion {
+        question: id,
+        index,
+        title,
+        description: payload
+            .description
+            .unwrap_or_else(|| "Вопрос из зала".to_string()),
+        answers: payload.answers.as_ref().map(|a| {
+            [
+                a.lie_delay.clone(),
+                a.lie_freeze.clone(),
+                a.delay_freeze.clone(),
+            ]
+        }),
+    };
+    let situation = promotion.situation();
+    validate_deck(std::slice::from_ref(&situation)).map_err(ApiError::BadRequest)?;
+    if let Some(q) = st.questions.get_mut(id) {
+        q.status = QuestionStatus::Promoted;
+    }
+    st.situations.insert(index, situation);
+    st.promoted.push(promotion);
+    info!(
+        room = ROOM,
+        question = id,
+        situation = index,
+        free_discussion = payload.answers.is_none(),
+        "question promoted"
+    );
+    Ok(Json(PromoteResponse { ok: true, index }))
+}
+
+// метрики для Prometheus
+async fn get_metrics(State(state): State<Shared>) -> Response {
+    let st = lock(&state);
+    // комната пока одна — та, что живёт в AppState
+    let body = st.metrics.render(1, st.current_index);
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+// замеряет время ответа по шаблону маршрута
+async fn track_latency(State(state): State<Shared>, req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+    let started = Instant::now();
+    let resp = next.run(req).await;
+    lock(&state)
+        .metrics
+        .observe_latency(&route, started.elapsed());
+    resp
+}
+
+// ===================== Утилиты =====================
+
+fn idx_to_reaction(i: usize) -> Reaction {
+    match i {
+        0 => Reaction::Lie,
+        1 => Reaction::Delay,
+        _ => Reaction::Freeze,
+    }
+}
+
+fn ordered_tuple(a: Reaction, b: Reaction) -> (Reaction, Reaction) {
+    if (a as u8) <= (b as u8) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Случайное число без отдельной зависимости: RandomState каждый раз сидируется заново.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn top_two(counts: &[u64; 3]) -> (Reaction, Reaction) {
+    let mut pairs = [
+        (counts[0], 0usize),
+        (counts[1], 1usize),
+        (counts[2], 2usize),
+    ];
+    pairs.sort_by_key(|p| std::cmp::Reverse(p.0));
+    (idx_to_reaction(pairs[0].1), idx_to_reaction(pairs[1].1))
 }
 
 // ===================== HTML (клиент) =====================
@@ -317,6 +2265,78 @@ const INDEX_HTML: &str = r#"<!doctype html>
       font-size: .72rem;
       margin-top: 6px;
     }
+    .notice {
+      color: #facc15;
+      font-size: .78rem;
+      margin-top: 8px;
+    }
+    .discussion { color: var(--accent); font-variant-numeric: tabular-nums; }
+    .ask textarea {
+      width: 100%;
+      margin: 10px 0 8px;
+      background: rgba(15, 23, 42, 0.5);
+      color: var(--text);
+      border: 1px solid rgba(148, 163, 184, .15);
+      border-radius: 12px;
+      padding: 8px 10px;
+      font: inherit;
+      font-size: .85rem;
+      resize: vertical;
+    }
+    .ask-btn, .wide-btn { justify-content: center; color: var(--text); font-weight: 600; width: 100%; }
+    .predict input {
+      width: 100%;
+      margin: 10px 0 8px;
+      background: rgba(15, 23, 42, 0.5);
+      color: var(--text);
+      border: 1px solid rgba(148, 163, 184, .15);
+      border-radius: 12px;
+      padding: 8px 10px;
+      font: inherit;
+    }
+    .predict select {
+      width: 100%;
+      margin: 0 0 8px;
+      background: rgba(15, 23, 42, 0.5);
+      color: var(--text);
+      border: 1px solid rgba(148, 163, 184, .15);
+      border-radius: 12px;
+      padding: 8px 10px;
+      font: inherit;
+    }
+    .emoji-row { display: flex; gap: 8px; margin-top: 10px; }
+    .emoji-btn {
+      flex: 1; font-size: 1.3rem; padding: 6px 0; cursor: pointer;
+      background: rgba(15, 23, 42, 0.5);
+      border: 1px solid rgba(148, 163, 184, .15);
+      border-radius: 12px;
+      transition: transform .06s ease-out;
+    }
+    .emoji-btn:active { transform: scale(.9); }
+    .teams { display: grid; grid-template-columns: repeat(auto-fit, minmax(140px, 1fr)); gap: 8px; margin-top: 8px; }
+    .team { background: rgba(15, 23, 42, 0.35); border-radius: 10px; padding: 7px 9px; font-size: .75rem; }
+    .team b { display: block; margin-bottom: 3px; }
+    .pairs { display: grid; grid-template-columns: repeat(3, 1fr); gap: 8px; margin: 10px 0 4px; }
+    .pair-btn { justify-content: center; color: var(--text); }
+    .pair-btn.chosen { border-color: var(--accent); background: rgba(56, 189, 248, .15); }
+    #predict-status { font-size: .72rem; min-height: 1.1rem; margin: 6px 0 4px; color: var(--muted); }
+    #ask-status { font-size: .72rem; min-height: 1.1rem; margin: 6px 0 4px; color: #22c55e; }
+    #rank-status { font-size: .72rem; min-height: 1.1rem; margin: 6px 0 4px; color: var(--muted); }
+    .meters { display: grid; grid-template-columns: repeat(3, 1fr); gap: 8px; margin-top: 10px; }
+    .meter { font-size: .68rem; color: var(--muted); }
+    .meter-bar { height: 5px; border-radius: 999px; background: rgba(148, 163, 184, .15); margin-top: 3px; overflow: hidden; }
+    .meter-fill { height: 100%; background: var(--accent); transition: width .4s ease-out; }
+    .meter-fill.low { background: #f43f5e; }
+    .changes { font-size: .72rem; margin-top: 4px; }
+    .changes .up { color: #22c55e; }
+    .changes .down { color: #f43f5e; }
+    .ending { border-color: rgba(244, 63, 94, .5); }
+    .comparison { display: grid; gap: 6px; margin-top: 8px; }
+    .compare { background: rgba(15, 23, 42, 0.35); border: 1px solid transparent; border-radius: 10px; padding: 7px 9px; font-size: .75rem; }
+    .compare.chosen { border-color: var(--accent); }
+    .compare b { display: block; margin-bottom: 3px; }
+    .compare p { margin: 3px 0 0; }
+    .ending h2 { color: #f43f5e; }
     @media (min-width: 520px) {
       .buttons { grid-template-columns: repeat(3, minmax(0, 1fr)); }
     }
@@ -335,9 +2355,32 @@ const INDEX_HTML: &str = r#"<!doctype html>
       <h2 id="title">Загрузка…</h2>
       <p id="desc">Получаем ситуацию с сервера</p>
       <p id="error" class="error" style="display:none;"></p>
+      <p id="notice" class="notice" style="display:none;"></p>
+      <p id="discussion" class="notice discussion" style="display:none;"></p>
+      <div class="meters" id="meters"></div>
+    </div>
+
+    <div class="card ending" id="ending" style="display:none;">
+      <h2 id="ending-title"></h2>
+      <p id="ending-text"></p>
     </div>
 
-    <div class="buttons">
+    <div class="card rank" id="rank-card" style="display:none;">
+      <h2>Расставь по местам</h2>
+      <p>Нажимай реакции от лучшей к худшей. Хватит и двух.</p>
+      <div class="pairs">
+        <button class="btn pair-btn" data-r="lie" onclick="rankPick(this)">🗯 Врать</button>
+        <button class="btn pair-btn" data-r="delay" onclick="rankPick(this)">⏱ Отложить</button>
+        <button class="btn pair-btn" data-r="freeze" onclick="rankPick(this)">🧊 Заморозить</button>
+      </div>
+      <div class="pairs">
+        <button class="btn pair-btn" onclick="rankClear()">Заново</button>
+        <button class="btn pair-btn" id="rank-send" onclick="sendRanking()" style="grid-column: span 2;">Отправить</button>
+      </div>
+      <div id="rank-status"></div>
+    </div>
+
+    <div class="buttons" id="click-buttons">
       <button class="btn" onclick="sendReaction('lie')">
         <div class="btn-icon">🗯</div>
         <div>
@@ -369,145 +2412,1542 @@ const INDEX_HTML: &str = r#"<!doctype html>
       <div class="answer-counts">
         Клики (врать, отложить, заморозить): <span id="answer-counts"></span>
       </div>
+      <div class="changes" id="answer-changes"></div>
+      <div class="teams" id="answer-teams"></div>
+      <div class="comparison" id="answer-comparison"></div>
+      <div class="emoji-row">
+        <button class="emoji-btn" onclick="sendEmoji('facepalm')">🤦</button>
+        <button class="emoji-btn" onclick="sendEmoji('laugh')">😂</button>
+        <button class="emoji-btn" onclick="sendEmoji('fire')">🔥</button>
+      </div>
     </div>
-  </div>
 
-  <script>
-    let currentTitle = null;
+    <div class="card predict" id="predict-card" style="display:none;">
+      <h2 id="predict-heading">Прогноз</h2>
+      <div id="predict-join">
+        <p id="predict-intro">Угадай, какую пару выберет зал, и заработай очки.</p>
+        <input id="player-name" maxlength="32" placeholder="Как тебя записать?" />
+        <select id="player-team" style="display:none;"></select>
+        <button class="btn wide-btn" onclick="registerPlayer()">Играть</button>
+      </div>
+      <div id="predict-play" style="display:none;">
+        <p><span id="player-label"></span><span id="points-row"> · очков: <b id="player-points">0</b></span></p>
+        <div class="pairs" id="pairs">
+          <button class="btn pair-btn" data-pair="lie,delay" onclick="predict(this)">🗯 + ⏱</button>
+          <button class="btn pair-btn" data-pair="lie,freeze" onclick="predict(this)">🗯 + 🧊</button>
+          <button class="btn pair-btn" data-pair="delay,freeze" onclick="predict(this)">⏱ + 🧊</button>
+        </div>
+      </div>
+      <div id="predict-status"></div>
+    </div>
+
+    <div class="card ask">
+      <h2>Свой неудобный вопрос</h2>
+      <p>Анонимно. Ведущий посмотрит и, может быть, вынесет на общее голосование.</p>
+      <textarea id="question" maxlength="500" rows="3" placeholder="А почему…"></textarea>
+      <button class="btn ask-btn" onclick="sendQuestion()">Спросить анонимно</button>
+      <div id="ask-status"></div>
+    </div>
+  </div>
+
+  <script>
+    let currentTitle = null;
+
+    async function sendReaction(reaction) {
+      await fetch('/api/click', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify(playerId ? {reaction, player: playerId} : {reaction})
+      });
+      document.getElementById('status').innerText = 'Принято, тыкай еще!!!';
+    }
+
+    let playerId = localStorage.getItem('playerId');
+    let teams = [];
+    let predictionMode = false;
+
+    function renderTeamPicker() {
+      const sel = document.getElementById('player-team');
+      sel.style.display = teams.length ? 'block' : 'none';
+      if (sel.options.length === teams.length + 1) return;
+      sel.innerHTML = '<option value="">Команда: распределите меня</option>';
+      teams.forEach((t, i) => {
+        const o = document.createElement('option');
+        o.value = i;
+        o.innerText = t;
+        sel.appendChild(o);
+      });
+    }
+
+    async function registerPlayer() {
+      const name = document.getElementById('player-name').value.trim();
+      if (!name) return;
+      const team = document.getElementById('player-team').value;
+      const body = {name};
+      if (team !== '') body.team = Number(team);
+      const r = await fetch('/api/players', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify(body)
+      });
+      const d = await r.json();
+      if (d.ok) {
+        playerId = d.id;
+        localStorage.setItem('playerId', d.id);
+        refreshPlayer();
+      } else {
+        document.getElementById('predict-status').innerText = d.error;
+      }
+    }
+
+    async function refreshPlayer() {
+      const joined = !!playerId;
+      if (joined) {
+        const r = await fetch('/api/players/' + playerId);
+        if (r.status === 404) {
+          // сервер забыл игрока — представляемся заново
+          playerId = null;
+          localStorage.removeItem('playerId');
+          return refreshPlayer();
+        }
+        const d = await r.json();
+        document.getElementById('player-label').innerText =
+          d.name + (d.team !== null && teams[d.team] ? ' (' + teams[d.team] + ')' : '');
+        document.getElementById('player-points').innerText = d.points;
+        const chosen = d.prediction && d.prediction.pair ? d.prediction.pair.join(',') : null;
+        for (const b of document.querySelectorAll('.pair-btn')) {
+          b.classList.toggle('chosen', b.dataset.pair === chosen);
+        }
+      }
+      document.getElementById('predict-join').style.display = joined ? 'none' : 'block';
+      document.getElementById('predict-play').style.display = joined ? 'block' : 'none';
+      document.getElementById('pairs').style.display = predictionMode ? 'grid' : 'none';
+      document.getElementById('points-row').style.display = predictionMode ? 'inline' : 'none';
+      document.getElementById('predict-heading').innerText = predictionMode ? 'Прогноз' : 'Команда';
+      document.getElementById('predict-intro').innerText = predictionMode
+        ? 'Угадай, какую пару выберет зал, и заработай очки.'
+        : 'Представься и выбери команду — потом сравним, как голосовали команды.';
+    }
+
+    async function predict(btn) {
+      const r = await fetch('/api/predict', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify({player: playerId, pair: btn.dataset.pair.split(',')})
+      });
+      const d = await r.json();
+      document.getElementById('predict-status').innerText = d.ok ? 'Прогноз принят' : d.error;
+      refreshPlayer();
+    }
+
+    function sendEmoji(emoji) {
+      fetch('/api/emoji', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify({emoji})
+      }).catch(() => {});
+    }
+
+    let ranking = [];
+
+    function renderRanking() {
+      for (const b of document.querySelectorAll('[data-r]')) {
+        const place = ranking.indexOf(b.dataset.r);
+        b.classList.toggle('chosen', place >= 0);
+        const label = b.innerText.replace(/^\d\.\s*/, '');
+        b.innerText = place >= 0 ? (place + 1) + '. ' + label : label;
+      }
+      document.getElementById('rank-send').disabled = ranking.length < 2;
+    }
+
+    function rankPick(btn) {
+      if (!ranking.includes(btn.dataset.r)) ranking.push(btn.dataset.r);
+      renderRanking();
+    }
+
+    function rankClear() {
+      ranking = [];
+      document.getElementById('rank-status').innerText = '';
+      renderRanking();
+    }
+
+    async function sendRanking() {
+      const body = playerId ? {ranking, player: playerId} : {ranking};
+      const r = await fetch('/api/rank', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify(body)
+      });
+      const d = await r.json();
+      document.getElementById('rank-status').innerText = d.ok
+        ? (playerId ? 'Голос принят, можно переголосовать' : 'Голос принят')
+        : d.error;
+    }
+
+    function renderMeters(box, list) {
+      box.innerHTML = '';
+      for (const m of list) {
+        const pct = Math.round((m.value - m.min) * 100 / Math.max(1, m.max - m.min));
+        const el = document.createElement('div');
+        el.className = 'meter';
+        el.innerText = m.label + ': ' + m.value;
+        const bar = document.createElement('div');
+        bar.className = 'meter-bar';
+        const fill = document.createElement('div');
+        fill.className = 'meter-fill' + (pct <= 20 ? ' low' : '');
+        fill.style.width = pct + '%';
+        bar.appendChild(fill);
+        el.appendChild(bar);
+        box.appendChild(el);
+      }
+    }
+
+    function renderDiscussion(d) {
+      const el = document.getElementById('discussion');
+      el.style.display = d && !d.pending ? 'block' : 'none';
+      if (!d || d.pending) return;
+      const secs = Math.ceil(d.left_ms / 1000);
+      el.innerText = d.over
+        ? '💬 Время обсуждения вышло'
+        : '💬 Обсуждаем: ' + Math.floor(secs / 60) + ':' + String(secs % 60).padStart(2, '0');
+    }
+
+    function renderChanges(box, list) {
+      box.innerHTML = '';
+      for (const c of list || []) {
+        const el = document.createElement('span');
+        el.className = c.delta > 0 ? 'up' : 'down';
+        el.innerText = c.label + ' ' + (c.delta > 0 ? '+' : '') + c.delta + '  ';
+        box.appendChild(el);
+      }
+    }
+
+    function renderEnding(ending) {
+      document.getElementById('ending').style.display = ending ? 'block' : 'none';
+      if (!ending) return;
+      document.getElementById('ending-title').innerText = ending.title;
+      document.getElementById('ending-text').innerText = ending.text;
+    }
+
+    const PAIR_ICONS = {lie: '🗯', delay: '⏱', freeze: '🧊'};
+
+    function renderComparison(box, c) {
+      box.innerHTML = '';
+      for (const e of c ? c.entries : []) {
+        const el = document.createElement('div');
+        el.className = 'compare' + (e.chosen ? ' chosen' : '');
+        const head = document.createElement('b');
+        head.innerText = e.pair.map(p => PAIR_ICONS[p]).join(' + ') + ' — ' + e.share + '%' +
+          (e.chosen ? ' · показали' : '');
+        el.appendChild(head);
+        for (const a of e.answers) {
+          const p = document.createElement('p');
+          p.innerText = a;
+          el.appendChild(p);
+        }
+        box.appendChild(el);
+      }
+    }
+
+    function renderTeams(box, list) {
+      box.innerHTML = '';
+      for (const t of list || []) {
+        const el = document.createElement('div');
+        el.className = 'team';
+        const name = document.createElement('b');
+        name.innerText = t.name + ' (' + t.counts.join(', ') + ')';
+        el.appendChild(name);
+        el.appendChild(document.createTextNode(t.answer));
+        box.appendChild(el);
+      }
+    }
+
+    async function sendQuestion() {
+      const field = document.getElementById('question');
+      const status = document.getElementById('ask-status');
+      const text = field.value.trim();
+      if (!text) return;
+      const r = await fetch('/api/questions', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify({text})
+      });
+      const d = await r.json();
+      if (d.ok) {
+        field.value = '';
+        status.style.color = '#22c55e';
+        status.innerText = 'Вопрос ушёл ведущему. Никто не узнает, что это ты.';
+      } else {
+        status.style.color = '#f43f5e';
+        status.innerText = d.error;
+      }
+    }
+
+    async function pollLoop() {
+      try {
+        // 1. тянем ситуацию
+        const cur = await fetch('/api/current');
+        const curData = await cur.json();
+        const notice = document.getElementById('notice');
+        notice.innerText = curData.notice || '';
+        notice.style.display = curData.notice ? 'block' : 'none';
+        predictionMode = curData.prediction_mode;
+        teams = curData.teams;
+        renderTeamPicker();
+        renderMeters(document.getElementById('meters'), curData.meters);
+        renderEnding(curData.ending);
+        renderDiscussion(curData.discussion);
+        const ranked = curData.voting !== 'clicks';
+        document.getElementById('rank-card').style.display = ranked ? 'block' : 'none';
+        document.getElementById('click-buttons').style.display = ranked ? 'none' : '';
+        const needsPlayer = predictionMode || teams.length > 0;
+        document.getElementById('predict-card').style.display = needsPlayer ? 'block' : 'none';
+        if (needsPlayer) refreshPlayer();
+        if (curData.title !== currentTitle) {
+          currentTitle = curData.title;
+          document.getElementById('predict-status').innerText = '';
+          document.getElementById('title').innerText = curData.title;
+          document.getElementById('desc').innerText = curData.description;
+          // при смене ситуации можно скрыть старый ответ
+          document.getElementById('answer-box').style.display = 'none';
+          rankClear();
+        }
+
+        // 2. тянем ответ
+        const res = await fetch('/api/result');
+        const resData = await res.json();
+        const box = document.getElementById('answer-box');
+        if (resData) {
+          box.style.display = 'block';
+          // пока идёт показ на экране, ответа ещё нет — только полоски и отсчёт
+          const stage = resData.reveal.stage;
+          document.getElementById('answer-text').innerText = stage === 'answer'
+            ? resData.answer
+            : stage === 'countdown'
+              ? '🥁 ' + Math.ceil(resData.reveal.left_ms / 1000) + '…'
+              : 'Смотрим, как голосовали…';
+          document.getElementById('answer-counts').innerText = resData.counts.join(', ');
+          renderChanges(document.getElementById('answer-changes'), resData.changes);
+          renderTeams(document.getElementById('answer-teams'), resData.teams);
+          renderComparison(document.getElementById('answer-comparison'), resData.comparison);
+        } else {
+          // если админ сбросил/переключил
+          box.style.display = 'none';
+          document.getElementById('status').innerText = '';
+        }
+
+      } catch (e) {
+        // можно залогать в консоль
+        // console.error(e);
+      } finally {
+        setTimeout(pollLoop, 1500);
+      }
+    }
+
+    // старт
+    pollLoop();
+  </script>
+</body>
+</html>
+"#;
+
+// ===================== HTML (админ) =====================
+
+const ADMIN_HTML: &str = r#"<!doctype html>
+<html lang="ru">
+<head>
+  <meta charset="utf-8" />
+  <title>Админ — Корпокликер</title>
+  <meta name="viewport" content="width=device-width,initial-scale=1" />
+  <style>
+    body {
+      font-family: system-ui, -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif;
+      background: #0f172a;
+      color: #e2e8f0;
+      max-width: 620px;
+      margin: 28px auto;
+      padding: 0 14px 30px;
+    }
+    h1 { font-size: 1.1rem; margin-bottom: 10px; }
+    .panel {
+      background: rgba(15, 23, 42, 0.35);
+      border: 1px solid rgba(148, 163, 184, 0.1);
+      border-radius: 16px;
+      padding: 14px 12px 10px;
+      backdrop-filter: blur(10px);
+    }
+    button {
+      background: rgba(15, 23, 42, 0.7);
+      border: 1px solid rgba(148, 163, 184, 0.25);
+      border-radius: 999px;
+      padding: 7px 15px;
+      font-size: .8rem;
+      color: #e2e8f0;
+      cursor: pointer;
+      margin-right: 6px;
+      margin-bottom: 6px;
+      transition: background .08s ease-out;
+    }
+    button:hover { background: rgba(15, 23, 42, 1); }
+    h2 { font-size: .9rem; margin: 0 0 8px; }
+    .upcoming { font-size: .75rem; color: #94a3b8; margin-top: 4px; }
+    .upcoming b { color: #e2e8f0; }
+    .upcoming .branch { color: #facc15; }
+    .script { font-size: .8rem; margin-top: 8px; padding: 8px 12px; border-left: 2px solid #38bdf8; background: rgba(2,6,23,.25); border-radius: 6px; }
+    .script .timer { color: #94a3b8; margin-left: 6px; font-variant-numeric: tabular-nums; }
+    .script .timer.over { color: #facc15; }
+    .script p { margin: 6px 0 0; white-space: pre-wrap; }
+    .script ul { margin: 6px 0 0; padding-left: 18px; color: #cbd5e1; }
+    .teams-row { display: flex; gap: 6px; align-items: center; margin-bottom: 6px; }
+    .teams-row input {
+      flex: 1; background: rgba(2,6,23,.25); color: #e2e8f0;
+      border: 1px solid rgba(148,163,184,.15); border-radius: 999px; padding: 6px 12px; font: inherit; font-size: .8rem;
+    }
+    .teams-row button { margin: 0; }
+    .teams-row select {
+      flex: 1; background: rgba(2,6,23,.25); color: #e2e8f0;
+      border: 1px solid rgba(148,163,184,.15); border-radius: 999px; padding: 6px 12px; font: inherit; font-size: .8rem;
+    }
+    .muted { font-size: .75rem; color: #94a3b8; }
+    .override { font-size: .8rem; margin: 4px 0 8px; }
+    .override summary { cursor: pointer; color: #94a3b8; margin-bottom: 6px; }
+    .override textarea {
+      width: 100%; box-sizing: border-box; margin-bottom: 6px;
+      background: rgba(2,6,23,.25); color: #e2e8f0;
+      border: 1px solid rgba(148,163,184,.15); border-radius: 8px;
+      padding: 6px 8px; font: inherit; font-size: .8rem;
+    }
+    .override input { flex: 0 0 110px; }
+    .questions { margin-top: 12px; }
+    .notes { margin-top: 12px; }
+    .notes textarea {
+      width: 100%; box-sizing: border-box; margin-bottom: 6px;
+      background: rgba(2,6,23,.25); color: #e2e8f0;
+      border: 1px solid rgba(148,163,184,.15); border-radius: 8px;
+      padding: 6px 8px; font: inherit; font-size: .8rem;
+    }
+    .notes li { font-size: .8rem; margin-bottom: 4px; }
+    .notes li button { padding: 0 8px; margin: 0 0 0 6px; }
+    .notes .action { color: #facc15; }
+    .notes a { color: #38bdf8; font-size: .75rem; margin-right: 10px; }
+    .q {
+      border-top: 1px solid rgba(148, 163, 184, 0.1);
+      padding: 8px 0 4px;
+    }
+    .q textarea {
+      width: 100%; box-sizing: border-box;
+      background: rgba(2,6,23,.25); color: #e2e8f0;
+      border: 1px solid rgba(148,163,184,.15); border-radius: 8px;
+      padding: 6px 8px; font: inherit; font-size: .8rem; margin-bottom: 6px;
+    }
+    .q .status { font-size: .7rem; color: #94a3b8; margin-bottom: 4px; }
+    .q details { font-size: .75rem; margin-bottom: 6px; }
+    .q details input {
+      width: 100%; box-sizing: border-box; margin-top: 4px;
+      background: rgba(2,6,23,.25); color: #e2e8f0;
+      border: 1px solid rgba(148,163,184,.15); border-radius: 6px; padding: 4px 6px;
+    }
+    .join {
+      margin-top: 12px;
+      display: flex; gap: 14px; align-items: center;
+    }
+    .join img { width: 120px; height: 120px; background: #fff; border-radius: 8px; }
+    .join-label { font-size: .75rem; color: #94a3b8; margin-bottom: 4px; }
+    .join-url { font-size: .85rem; margin-bottom: 8px; word-break: break-all; }
+    .join-label b { color: #e2e8f0; font-size: 1rem; letter-spacing: .1em; }
+    pre {
+      white-space: pre-wrap;
+      background: rgba(2,6,23,.25);
+      border: 1px solid rgba(148,163,184,.05);
+      padding: 10px;
+      border-radius: 10px;
+      margin-top: 10px;
+      font-size: .75rem;
+    }
+  </style>
+</head>
+<body>
+  <h1>Админ — Корпокликер</h1>
+  <div class="panel">
+    <button onclick="previewAnswer()">Черновик</button>
+    <button onclick="showAnswer()">Показать ответ</button>
+    <button onclick="skipReveal()">Пропустить этап</button>
+    <button onclick="revealAll()">Все ответы</button>
+    <button onclick="nextSituation()">Дальше</button>
+    <button onclick="resetCounts()">Сброс</button>
+    <button onclick="restartGame()">Новая игра</button>
+    <button onclick="window.open('/present', '_blank')">Экран для проектора</button>
+    <button id="prediction-btn" onclick="togglePrediction()">Прогнозы: …</button>
+    <button onclick="showLeaderboard()">Итоги</button>
+    <button id="voting-btn" onclick="cycleVoting()">Голосование: …</button>
+    <div class="teams-row">
+      <select id="winner-select" onchange="saveWinner()">
+        <option value="top-two">Пара: два лидера</option>
+        <option value="weighted-random">Пара: случайно по голосам</option>
+        <option value="least-popular">Пара: назло залу</option>
+        <option value="lie,delay">Пара: всегда Врать + Отложить</option>
+        <option value="lie,freeze">Пара: всегда Врать + Заморозить</option>
+        <option value="delay,freeze">Пара: всегда Отложить + Заморозить</option>
+      </select>
+    </div>
+    <div class="teams-row">
+      <input id="teams-input" placeholder="Команды через запятую (пусто — без команд)" />
+      <button onclick="saveTeams()">Команды</button>
+    </div>
+    <details class="override">
+      <summary>Показать с правкой</summary>
+      <div class="teams-row">
+        <select id="ov-pair">
+          <option value="">Пара: как решил зал</option>
+          <option value="lie,delay">Врать + Отложить</option>
+          <option value="lie,freeze">Врать + Заморозить</option>
+          <option value="delay,freeze">Отложить + Заморозить</option>
+        </select>
+        <input id="ov-variant" type="number" min="1" placeholder="Вариант №" />
+      </div>
+      <textarea id="ov-answer" rows="2" placeholder="Свой ответ (можно {total}, {winner} и т.д.)"></textarea>
+      <button onclick="previewAnswer(overrideBody())">Черновик с правкой</button>
+      <button onclick="showAnswer(overrideBody())">Показать с правкой</button>
+    </details>
+    <div class="script" id="script">
+      <b id="script-title">…</b><span class="timer" id="script-timer"></span>
+      <p id="script-notes"></p>
+      <ul id="script-prompts"></ul>
+    </div>
+    <div class="teams-row">
+      <input id="discussion-secs" type="number" min="1" placeholder="Обсуждение, сек" />
+      <button onclick="startDiscussion()">Обсуждение</button>
+      <button onclick="stopDiscussion()">Стоп</button>
+    </div>
+    <div class="upcoming" id="upcoming"></div>
+    <div class="upcoming" id="meters"></div>
+    <pre id="out">Нажми “Показать ответ”, чтобы отдать его игрокам</pre>
+  </div>
+
+  <div class="panel notes">
+    <h2>Заметки и договорённости</h2>
+    <textarea id="note-text" rows="2" placeholder="Что обсудили или о чём договорились"></textarea>
+    <div class="teams-row">
+      <select id="note-kind">
+        <option value="note">Заметка</option>
+        <option value="action">Договорённость</option>
+      </select>
+      <button onclick="addNote()">Записать</button>
+    </div>
+    <ul id="notes"></ul>
+    <a href="/admin/export?format=markdown" target="_blank">Итоги сессии (markdown)</a>
+    <a href="/admin/export" target="_blank">JSON</a>
+    <div class="teams-row" id="tracker-row" style="display:none;">
+      <button onclick="pushToTracker()">В трекер</button>
+      <span class="muted" id="tracker-status"></span>
+    </div>
+    <pre class="muted" id="tracker-report"></pre>
+  </div>
+
+  <div class="panel questions">
+    <h2>Вопросы из зала</h2>
+    <div id="questions"><div class="muted">Пока пусто</div></div>
+  </div>
+
+  <div class="panel join">
+    <img src="/qr.svg" alt="QR для входа" />
+    <div>
+      <div class="join-label">Ссылка для игроков</div>
+      <div class="join-url" id="join-url"></div>
+      <div class="join-label" id="join-code-row" style="display:none;">
+        Код комнаты: <b id="join-code"></b> (вводить на <span id="join-page"></span>)
+      </div>
+    </div>
+  </div>
+
+  <script>
+    async function showAnswer(override) {
+      const r = override
+        ? await fetch('/admin/show', {
+            method: 'POST',
+            headers: {'Content-Type':'application/json'},
+            body: JSON.stringify(override)
+          })
+        : await fetch('/admin/show');
+      const d = await r.json();
+      if (d.ok === false) {
+        document.getElementById('out').innerText = d.error;
+        return;
+      }
+      document.getElementById('out').innerText =
+        (d.overridden ? '[с правкой ведущего]\n' : '') +
+        'Ситуация: ' + d.situation_title +
+        '\n\nОтвет' + (d.variants > 1 ? ' (вариант ' + (d.variant + 1) + ' из ' + d.variants + ')' : '') + ':\n' + d.answer +
+        '\n\nКлики (Врать, Отложить, Заморозить): ' + d.counts.join(', ') +
+        (d.teams ? d.teams.map(t =>
+          '\n\n— ' + t.name + ' (' + t.counts.join(', ') + '):\n' + t.answer).join('') : '') +
+        (d.leaderboard ? '\n\nЛидеры:\n' + formatLeaderboard(d.leaderboard) : '') +
+        (d.rule ? '\n\nСработало правило: ' + d.rule : '') +
+        (d.selection.strategy !== 'top-two' ? '\n\nПара выбрана: ' + WINNER_LABELS[d.selection.strategy] : '') +
+        (d.ranking ? '\n\n' + formatRanking(d.ranking) : '') +
+        (d.changes ? '\n\nШкалы: ' + formatChanges(d.changes) : '') +
+        (d.ending ? '\n\nКОНЕЦ ИГРЫ: ' + d.ending.title + '\n' + d.ending.text : '');
+    }
+    const WINNER_LABELS = {'weighted-random': 'случайно по голосам', 'least-popular': 'назло залу', override: 'ведущим'};
+    const VOTING_LABELS = {clicks: 'клики', borda: 'Борда', pairwise: 'попарно'};
+    const VOTING_ORDER = ['clicks', 'borda', 'pairwise'];
+    let voting = 'clicks';
+    function formatRanking(t) {
+      const names = ['Врать', 'Отложить', 'Заморозить'];
+      const idx = {lie: 0, delay: 1, freeze: 2};
+      return 'Рейтинг (' + VOTING_LABELS[t.method] + ', бюллетеней: ' + t.ballots + '):\n' +
+        t.order.map((r, i) => {
+          const k = idx[r];
+          return (i + 1) + '. ' + names[k] + ' — ' + t.scores[k] +
+            ' (места: ' + t.positions[k].join('/') + ')';
+        }).join('\n');
+    }
+    async function saveWinner() {
+      const v = document.getElementById('winner-select').value;
+      const body = v.includes(',') ? {strategy: 'override', pair: v.split(',')} : {strategy: v};
+      await fetch('/admin/winner', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify(body)
+      });
+      document.activeElement.blur();
+      loadMode();
+    }
+    async function cycleVoting() {
+      const mode = VOTING_ORDER[(VOTING_ORDER.indexOf(voting) + 1) % VOTING_ORDER.length];
+      await fetch('/admin/voting', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify({mode})
+      });
+      loadMode();
+    }
+    function overrideBody() {
+      const body = {};
+      const pair = document.getElementById('ov-pair').value;
+      const variant = document.getElementById('ov-variant').value;
+      const answer = document.getElementById('ov-answer').value.trim();
+      if (pair) body.pair = pair.split(',');
+      if (variant) body.variant = Number(variant) - 1;
+      if (answer) body.answer = answer;
+      return body;
+    }
+    async function previewAnswer(override) {
+      const r = override
+        ? await fetch('/admin/preview', {
+            method: 'POST',
+            headers: {'Content-Type':'application/json'},
+            body: JSON.stringify(override)
+          })
+        : await fetch('/admin/preview');
+      const d = await r.json();
+      const out = document.getElementById('out');
+      if (d.ok === false) {
+        out.innerText = d.error;
+        return;
+      }
+      out.innerText =
+        'ЧЕРНОВИК — игроки его не видят' + (d.overridden ? ' (с правкой)' : '') +
+        (d.binding ? '' : '\nПара или вариант при показе выпадут случайно — это лишь ожидаемый ответ') +
+        '\n\nПара: ' + d.pair.map(p => PAIR_LABELS[p]).join(' + ') +
+        (d.rule ? ' (правило: ' + d.rule + ')' : '') +
+        '\n\nОтвет' + (d.variants > 1 ? ' (вариант ' + (d.variant + 1) + ' из ' + d.variants + ')' : '') + ':\n' + d.answer +
+        '\n\nГолоса: ' + d.counts.join(', ') +
+        (d.teams ? d.teams.map(t => '\n\n— ' + t.name + ':\n' + t.answer).join('') : '') +
+        (d.changes.length ? '\n\nШкалы: ' + formatChanges(d.changes) : '') +
+        (d.ending ? '\n\nЭтот ответ закончит игру: ' + d.ending.title : '');
+    }
+    const STAGE_LABELS = {bars: 'полоски', countdown: 'отсчёт', answer: 'ответ'};
+    async function skipReveal() {
+      const r = await fetch('/admin/reveal/skip', {method: 'POST'});
+      const d = await r.json();
+      document.getElementById('out').innerText = d.ok === false
+        ? d.error
+        : 'Показ: ' + STAGE_LABELS[d.stage] + (d.left_ms ? ' (ещё ' + Math.ceil(d.left_ms / 1000) + ' с)' : '');
+    }
+    async function revealAll() {
+      const r = await fetch('/admin/reveal-all', {method: 'POST'});
+      const d = await r.json();
+      const out = document.getElementById('out');
+      if (d.ok === false) {
+        out.innerText = d.error;
+        return;
+      }
+      out.innerText = 'Игроки видят ответы на все пары:\n\n' + d.entries.map(e =>
+        (e.chosen ? '★ ' : '') + e.pair.map(p => PAIR_LABELS[p]).join(' + ') +
+        ' — ' + e.share + '% (' + e.votes + ')\n' + e.answers.join('\n')).join('\n\n');
+    }
+    function formatChanges(list) {
+      return list.map(c => c.label + ' ' + (c.delta > 0 ? '+' : '') + c.delta).join(', ');
+    }
+    async function nextSituation() {
+      const r = await fetch('/admin/next', {method:'POST'});
+      const d = await r.json();
+      document.getElementById('out').innerText = d.ok === false
+        ? d.error
+        : 'Переключено на следующую ситуацию, клики сброшены.';
+    }
+    async function restartGame() {
+      if (!confirm('Начать игру заново? Шкалы вернутся к началу, колода — к первой ситуации.')) return;
+      await fetch('/admin/restart', {method:'POST'});
+      document.getElementById('out').innerText = 'Новая игра: первая ситуация, шкалы на старте.';
+    }
+    async function loadJoinInfo() {
+      const r = await fetch('/api/join');
+      const d = await r.json();
+      document.getElementById('join-url').innerText = d.url;
+      if (d.code) {
+        document.getElementById('join-code').innerText = d.code;
+        document.getElementById('join-page').innerText = location.origin + '/join';
+        document.getElementById('join-code-row').style.display = 'block';
+      }
+    }
+    loadJoinInfo();
+
+    let predictionMode = false;
+    function renderPredictionBtn() {
+      document.getElementById('prediction-btn').innerText = 'Прогнозы: ' + (predictionMode ? 'вкл' : 'выкл');
+    }
+    async function loadMode() {
+      const r = await fetch('/api/current');
+      const d = await r.json();
+      predictionMode = d.prediction_mode;
+      renderPredictionBtn();
+      voting = d.voting;
+      const winnerSelect = document.getElementById('winner-select');
+      if (document.activeElement !== winnerSelect) {
+        winnerSelect.value = d.winner.strategy === 'override' ? d.winner.pair.join(',') : d.winner.strategy;
+      }
+      document.getElementById('voting-btn').innerText = 'Голосование: ' + VOTING_LABELS[voting];
+      document.getElementById('meters').innerText = d.meters.length
+        ? 'Шкалы: ' + d.meters.map(m => m.label + ' ' + m.value).join(' · ') +
+          (d.ending ? ' — игра окончена: ' + d.ending.title : '')
+        : '';
+      const input = document.getElementById('teams-input');
+      if (document.activeElement !== input) input.value = d.teams.join(', ');
+    }
+    async function saveTeams() {
+      const teams = document.getElementById('teams-input').value.split(',');
+      await fetch('/admin/teams', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify({teams})
+      });
+      document.activeElement.blur();
+      loadMode();
+    }
+    async function togglePrediction() {
+      await fetch('/admin/prediction', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify({enabled: !predictionMode})
+      });
+      loadMode();
+    }
+    function formatLeaderboard(list) {
+      if (!list.length) return 'Пока никто не играл в прогнозы.';
+      return list.map((e, i) => (i + 1) + '. ' + e.name + ' — ' + e.points).join('\n');
+    }
+    async function showLeaderboard() {
+      const r = await fetch('/api/leaderboard');
+      document.getElementById('out').innerText = 'Таблица лидеров:\n\n' + formatLeaderboard(await r.json());
+    }
+    loadMode();
+
+    const STATUS_LABELS = {
+      pending: 'ждёт модерации', approved: 'одобрен', rejected: 'отклонён', promoted: 'вынесен в игру'
+    };
+
+    function renderQuestions(list) {
+      const box = document.getElementById('questions');
+      // не перерисовываем, пока админ что-то правит
+      if (box.contains(document.activeElement)) return;
+      if (!list.length) {
+        box.innerHTML = '<div class="muted">Пока пусто</div>';
+        return;
+      }
+      box.innerHTML = '';
+      for (const q of list.slice().reverse()) {
+        const el = document.createElement('div');
+        el.className = 'q';
+        el.innerHTML =
+          '<div class="status">#' + q.id + ' · ' + STATUS_LABELS[q.status] + '</div>' +
+          '<textarea rows="2"></textarea>' +
+          (q.status === 'approved'
+            ? '<details><summary>Свои ответы (иначе — свободное обсуждение)</summary>' +
+              '<input placeholder="врать + отложить" data-k="lie_delay" />' +
+              '<input placeholder="врать + заморозить" data-k="lie_freeze" />' +
+              '<input placeholder="отложить + заморозить" data-k="delay_freeze" /></details>'
+            : '') +
+          '<div class="actions"></div>';
+        el.querySelector('textarea').value = q.text;
+        const actions = el.querySelector('.actions');
+        const add = (label, fn) => {
+          const b = document.createElement('button');
+          b.innerText = label;
+          b.onclick = fn;
+          actions.appendChild(b);
+        };
+        if (q.status !== 'promoted') {
+          add('Сохранить текст', () => moderate(q.id, {text: el.querySelector('textarea').value}));
+          if (q.status !== 'approved') add('Одобрить', () => moderate(q.id, {status: 'approved'}));
+          if (q.status !== 'rejected') add('Отклонить', () => moderate(q.id, {status: 'rejected'}));
+        }
+        if (q.status === 'approved') add('В игру следующим', () => promote(q.id, el));
+        box.appendChild(el);
+      }
+    }
+
+    async function startDiscussion() {
+      const secs = document.getElementById('discussion-secs').value;
+      const r = await fetch('/admin/discussion', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify(secs ? {secs: Number(secs)} : {})
+      });
+      const d = await r.json();
+      document.getElementById('out').innerText = d.ok === false
+        ? d.error
+        : 'Обсуждение: ' + formatClock(d.secs) + (d.pending ? ' — начнётся, когда откроется ответ' : '');
+    }
+    async function stopDiscussion() {
+      await fetch('/admin/discussion/stop', {method: 'POST'});
+      document.getElementById('out').innerText = 'Обсуждение закончено, время записано в итоги.';
+    }
+
+    async function loadNotes() {
+      try {
+        const list = await (await fetch('/admin/notes')).json();
+        const box = document.getElementById('notes');
+        box.innerHTML = '';
+        for (const n of list) {
+          const li = document.createElement('li');
+          if (n.kind === 'action') li.className = 'action';
+          li.innerText = (n.kind === 'action' ? '☐ ' : '') + n.text;
+          const del = document.createElement('button');
+          del.innerText = '×';
+          del.onclick = () => deleteNote(n.id);
+          li.appendChild(del);
+          box.appendChild(li);
+        }
+      } catch (e) {
+        // обновим на следующем тике
+      }
+    }
+    async function addNote() {
+      const field = document.getElementById('note-text');
+      const text = field.value.trim();
+      if (!text) return;
+      const r = await fetch('/admin/notes', {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify({kind: document.getElementById('note-kind').value, text})
+      });
+      const d = await r.json();
+      if (d.ok === false) {
+        alert(d.error);
+        return;
+      }
+      field.value = '';
+      loadNotes();
+    }
+    async function deleteNote(id) {
+      await fetch('/admin/notes/' + id, {method: 'DELETE'});
+      loadNotes();
+    }
+    loadNotes();
+    setInterval(loadNotes, 3000);
+
+    async function loadTracker() {
+      try {
+        const d = await (await fetch('/admin/tracker')).json();
+        document.getElementById('tracker-row').style.display = d.configured ? 'flex' : 'none';
+        document.getElementById('tracker-status').innerText = d.busy
+          ? 'выгружаем…'
+          : 'договорённостей к выгрузке: ' + d.pending + (d.pushed ? ', уже в трекере: ' + d.pushed : '');
+      } catch (e) {
+        // обновим на следующем тике
+      }
+    }
+    async function pushToTracker() {
+      const out = document.getElementById('tracker-report');
+      out.innerText = 'Выгружаем…';
+      const r = await fetch('/admin/tracker/push', {method: 'POST'});
+      const d = await r.json();
+      if (d.ok === false) {
+        out.innerText = d.error;
+        return;
+      }
+      out.innerText = 'Заведено: ' + d.delivered + ', ошибок: ' + d.failed +
+        (d.skipped ? ', уже были: ' + d.skipped : '') +
+        d.items.map(i => '\n' + (i.ok ? '✓ ' : '✗ ') + i.text +
+          (i.ok ? '' : ' — ' + i.error + ' (попыток: ' + i.attempts + ')')).join('');
+      loadTracker();
+    }
+    loadTracker();
+    setInterval(loadTracker, 5000);
+
+    async function loadQuestions() {
+      try {
+        const r = await fetch('/admin/questions');
+        renderQuestions(await r.json());
+      } catch (e) {
+        // следующая попытка по таймеру
+      }
+    }
+
+    async function moderate(id, body) {
+      const r = await fetch('/admin/questions/' + id, {
+        method: 'POST',
+        headers: {'Content-Type':'application/json'},
+        body: JSON.stringify(body)
+      });
+      const d = await r.json();
+      if (d.ok === false) alert(d.error);
+      document.activeElement.blur();
+      loadQuestions();
+    }
 
-    async function sendReaction(reaction) {
-      await fetch('/api/click', {
+    async function promote(id, el) {
+      const inputs = [...el.querySelectorAll('details input')];
+      const body = {};
+      if (inputs.some(i => i.value.trim())) {
+        body.answers = {};
+        for (const i of inputs) body.answers[i.dataset.k] = i.value.trim();
+      }
+      const r = await fetch('/admin/questions/' + id + '/promote', {
         method: 'POST',
         headers: {'Content-Type':'application/json'},
-        body: JSON.stringify({reaction})
+        body: JSON.stringify(body)
       });
-      document.getElementById('status').innerText = 'Принято, тыкай еще!!!';
+      const d = await r.json();
+      if (d.ok === false) {
+        alert(d.error);
+      } else {
+        document.getElementById('out').innerText = 'Вопрос вынесен в игру — он будет следующим после «Дальше».';
+      }
+      document.activeElement.blur();
+      loadQuestions();
     }
 
-    async function pollLoop() {
+    loadQuestions();
+    setInterval(loadQuestions, 3000);
+
+    const PAIR_LABELS = {lie: 'Врать', delay: 'Отложить', freeze: 'Заморозить'};
+    async function loadUpcoming() {
       try {
-        // 1. тянем ситуацию
-        const cur = await fetch('/api/current');
-        const curData = await cur.json();
-        if (curData.title !== currentTitle) {
-          currentTitle = curData.title;
-          document.getElementById('title').innerText = curData.title;
-          document.getElementById('desc').innerText = curData.description;
-          // при смене ситуации можно скрыть старый ответ
-          document.getElementById('answer-box').style.display = 'none';
+        const d = await (await fetch('/admin/upcoming')).json();
+        const el = document.getElementById('upcoming');
+        el.innerHTML = '';
+        el.appendChild(document.createTextNode('Дальше: '));
+        const title = document.createElement('b');
+        title.innerText = d.title;
+        el.appendChild(title);
+        if (d.via_branch) {
+          const b = document.createElement('span');
+          b.className = 'branch';
+          b.innerText = ' — ветка ' + d.pair.map(p => PAIR_LABELS[p]).join(' + ');
+          el.appendChild(b);
         }
+      } catch (e) {
+        // обновим на следующем тике
+      }
+    }
+    loadUpcoming();
+    setInterval(loadUpcoming, 2000);
 
-        // 2. тянем ответ
-        const res = await fetch('/api/result');
-        const resData = await res.json();
-        const box = document.getElementById('answer-box');
-        if (resData) {
-          box.style.display = 'block';
-          document.getElementById('answer-text').innerText = resData.answer;
-          document.getElementById('answer-counts').innerText = resData.counts.join(', ');
-        } else {
-          // если админ сбросил/переключил
-          box.style.display = 'none';
-          document.getElementById('status').innerText = '';
+    // шпаргалка к текущей ситуации; таймер считает от её появления на этой странице
+    let scriptIndex = null;
+    let scriptSince = 0;
+    let scriptDuration = null;
+    function formatClock(secs) {
+      return Math.floor(secs / 60) + ':' + String(secs % 60).padStart(2, '0');
+    }
+    async function loadScript() {
+      try {
+        const d = await (await fetch('/admin/script')).json();
+        if (d.index === scriptIndex) return;
+        scriptIndex = d.index;
+        scriptSince = Date.now();
+        scriptDuration = d.duration_secs;
+        document.getElementById('script-title').innerText = (d.index + 1) + '/' + d.total + ' · ' + d.title;
+        document.getElementById('script-notes').innerText = d.notes;
+        const list = document.getElementById('script-prompts');
+        list.innerHTML = '';
+        for (const p of d.prompts) {
+          const li = document.createElement('li');
+          li.innerText = p;
+          list.appendChild(li);
         }
-
+        tickScript();
       } catch (e) {
-        // можно залогать в консоль
-        // console.error(e);
-      } finally {
-        setTimeout(pollLoop, 1500);
+        // обновим на следующем тике
       }
     }
+    function tickScript() {
+      if (scriptIndex === null) return;
+      const secs = Math.floor((Date.now() - scriptSince) / 1000);
+      const el = document.getElementById('script-timer');
+      el.innerText = '⏱ ' + formatClock(secs) + (scriptDuration ? ' / ~' + formatClock(scriptDuration) : '');
+      el.classList.toggle('over', !!scriptDuration && secs > scriptDuration);
+    }
+    loadScript();
+    setInterval(loadScript, 2000);
+    setInterval(tickScript, 1000);
+    setInterval(loadMode, 3000);
 
-    // старт
-    pollLoop();
+    async function resetCounts() {
+      await fetch('/admin/reset', {method:'POST'});
+      document.getElementById('out').innerText = 'Клики и показанный ответ сброшены.';
+    }
   </script>
 </body>
 </html>
 "#;
 
-// ===================== HTML (админ) =====================
+// ===================== HTML (вход по коду) =====================
 
-const ADMIN_HTML: &str = r#"<!doctype html>
+const JOIN_HTML: &str = r#"<!doctype html>
 <html lang="ru">
 <head>
   <meta charset="utf-8" />
-  <title>Админ — Корпокликер</title>
+  <title>Вход — Корпокликер</title>
   <meta name="viewport" content="width=device-width,initial-scale=1" />
   <style>
     body {
+      margin: 0;
+      min-height: 100vh;
+      background: radial-gradient(circle at top, #0f172a 0, #020617 60%, #020617 100%);
       font-family: system-ui, -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif;
-      background: #0f172a;
       color: #e2e8f0;
-      max-width: 620px;
-      margin: 28px auto;
-      padding: 0 14px 30px;
+      display: grid; place-items: center;
+      padding: 18px;
     }
-    h1 { font-size: 1.1rem; margin-bottom: 10px; }
-    .panel {
-      background: rgba(15, 23, 42, 0.35);
-      border: 1px solid rgba(148, 163, 184, 0.1);
-      border-radius: 16px;
-      padding: 14px 12px 10px;
-      backdrop-filter: blur(10px);
+    form {
+      background: rgba(2, 6, 23, 0.45);
+      border: 1px solid rgba(148, 163, 184, .12);
+      border-radius: 18px;
+      padding: 20px;
+      width: min(340px, 100%);
+      display: grid; gap: 12px;
+    }
+    h1 { font-size: 1.05rem; margin: 0; }
+    input {
+      font-size: 1.6rem; letter-spacing: .3em; text-align: center; text-transform: uppercase;
+      background: rgba(15, 23, 42, 0.7); color: #e2e8f0;
+      border: 1px solid rgba(148, 163, 184, .25); border-radius: 12px; padding: 10px;
     }
     button {
-      background: rgba(15, 23, 42, 0.7);
-      border: 1px solid rgba(148, 163, 184, 0.25);
-      border-radius: 999px;
-      padding: 7px 15px;
-      font-size: .8rem;
-      color: #e2e8f0;
-      cursor: pointer;
-      margin-right: 6px;
-      margin-bottom: 6px;
-      transition: background .08s ease-out;
+      background: #38bdf8; color: #020617; font-weight: 700;
+      border: 0; border-radius: 12px; padding: 10px; font-size: .95rem; cursor: pointer;
     }
-    button:hover { background: rgba(15, 23, 42, 1); }
-    pre {
-      white-space: pre-wrap;
-      background: rgba(2,6,23,.25);
-      border: 1px solid rgba(148,163,184,.05);
-      padding: 10px;
-      border-radius: 10px;
-      margin-top: 10px;
-      font-size: .75rem;
+    .error { color: #f43f5e; font-size: .8rem; display: none; }
+  </style>
+</head>
+<body>
+  <form onsubmit="go(event)">
+    <h1>Код комнаты</h1>
+    <input id="code" maxlength="8" autocomplete="off" autofocus />
+    <div class="error" id="error">Такого кода нет, проверь ещё раз</div>
+    <button type="submit">Войти</button>
+  </form>
+  <script>
+    if (new URLSearchParams(location.search).has('bad')) {
+      document.getElementById('error').style.display = 'block';
+    }
+    function go(e) {
+      e.preventDefault();
+      const code = document.getElementById('code').value.trim();
+      if (code) location.href = '/r/' + encodeURIComponent(code);
+    }
+  </script>
+</body>
+</html>
+"#;
+
+// ===================== HTML (проектор) =====================
+
+const PRESENT_HTML: &str = r#"<!doctype html>
+<html lang="ru">
+<head>
+  <meta charset="utf-8" />
+  <title>Корпокликер — экран</title>
+  <meta name="viewport" content="width=device-width,initial-scale=1" />
+  <style>
+    :root {
+      --text: #e2e8f0;
+      --muted: #94a3b8;
+      --accent: #38bdf8;
+    }
+    * { box-sizing: border-box; }
+    html, body { height: 100%; }
+    body {
+      margin: 0;
+      background: radial-gradient(circle at top, #0f172a 0, #020617 60%, #020617 100%);
+      font-family: system-ui, -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif;
+      color: var(--text);
+      display: grid;
+      grid-template-rows: auto 1fr auto;
+      padding: 3vh 4vw;
+      overflow: hidden;
+    }
+    .top { display: flex; justify-content: space-between; align-items: center; font-size: 2.2vh; color: var(--muted); }
+    .join { display: flex; gap: 2vw; align-items: center; }
+    .join img { width: 16vh; height: 16vh; background: #fff; border-radius: 1vh; }
+    .join b { color: var(--text); }
+    .join .code { font-size: 4vh; letter-spacing: .15em; color: var(--accent); }
+    .voters b { color: var(--accent); font-size: 3.4vh; }
+    .stage { display: flex; flex-direction: column; justify-content: center; }
+    h1 { font-size: 6.5vh; line-height: 1.15; margin: 0 0 2vh; }
+    .desc { font-size: 3vh; color: var(--muted); margin: 0; }
+    .reveal { margin-top: 5vh; display: none; }
+    .reveal.on { display: block; animation: rise .6s ease-out; }
+    .bars { display: grid; gap: 1.4vh; margin-bottom: 4vh; }
+    .bars-caption { font-size: 2.2vh; color: var(--muted); margin-bottom: 1.4vh; }
+    .bar { display: grid; grid-template-columns: 22vw 1fr 8vw; align-items: center; gap: 1.5vw; font-size: 2.8vh; }
+    .track { height: 4vh; background: rgba(148, 163, 184, .12); border-radius: 999px; overflow: hidden; }
+    .fill { height: 100%; width: 0; background: var(--accent); border-radius: 999px; transition: width 1.2s cubic-bezier(.2, .8, .2, 1); }
+    .num { text-align: right; font-variant-numeric: tabular-nums; }
+    .answer {
+      font-size: 4.4vh; line-height: 1.3;
+      background: rgba(15, 23, 42, .55);
+      border: 1px solid rgba(148, 163, 184, .15);
+      border-radius: 2vh; padding: 3vh 3vw;
+      opacity: 0; transform: translateY(2vh);
+      transition: opacity .8s ease-out 1s, transform .8s ease-out 1s;
+    }
+    .reveal.on .answer { opacity: 1; transform: none; }
+    .team-answers {
+      display: grid; grid-template-columns: repeat(auto-fit, minmax(30vw, 1fr)); gap: 2vw;
+      margin-top: 3vh; opacity: 0; transition: opacity .8s ease-out 1.6s;
+    }
+    .reveal.on .team-answers { opacity: 1; }
+    /* постепенный показ: пока идут полоски и отсчёт, ответа нет */
+    .drumroll { display: none; text-align: center; font-size: 16vh; font-weight: 700; color: var(--accent); }
+    .reveal.countdown .drumroll { display: block; animation: pulse 1s ease-in-out infinite; }
+    .reveal.waiting .answer, .reveal.waiting .changes, .reveal.waiting .team-answers, .reveal.waiting .leaders { display: none; }
+    .reveal.staged .answer, .reveal.staged .changes, .reveal.staged .team-answers, .reveal.staged .leaders { transition: none; animation: rise .8s ease-out; }
+    @keyframes pulse { 50% { transform: scale(1.15); } }
+    .discussion { font-size: 5vh; font-weight: 700; color: var(--accent); font-variant-numeric: tabular-nums; }
+    .discussion.over { color: #f43f5e; animation: pulse 1s ease-in-out infinite; }
+    .comparison { display: grid; grid-template-columns: repeat(3, 1fr); gap: 2vw; margin-top: 3vh; }
+    .comparison:empty { display: none; }
+    .compare {
+      background: rgba(15, 23, 42, .45); border: 1px solid rgba(148, 163, 184, .12);
+      border-radius: 1.6vh; padding: 2vh 2vw; font-size: 2.4vh; line-height: 1.3;
+      animation: rise .6s ease-out both;
+    }
+    .compare.chosen { border-color: var(--accent); box-shadow: 0 0 3vh rgba(56, 189, 248, .25); }
+    .compare b { display: flex; justify-content: space-between; color: var(--accent); margin-bottom: 1vh; font-size: 2.8vh; }
+    .compare p { margin: 0 0 1vh; }
+    .team-answer {
+      background: rgba(15, 23, 42, .45); border: 1px solid rgba(148, 163, 184, .12);
+      border-radius: 1.6vh; padding: 2vh 2vw; font-size: 2.8vh; line-height: 1.3;
+    }
+    .team-answer b { display: block; color: var(--accent); margin-bottom: 1vh; }
+    .team-answer small { display: block; color: var(--muted); margin-top: 1vh; font-size: 2vh; }
+    .leaders {
+      margin: 3vh 0 0; padding: 0 0 0 3vw;
+      font-size: 2.6vh; color: var(--muted);
+      columns: 2; opacity: 0; transition: opacity .8s ease-out 2s;
+    }
+    .leaders b { color: var(--text); }
+    .reveal.on .leaders { opacity: 1; }
+    .bursts { position: fixed; inset: 0; pointer-events: none; overflow: hidden; }
+    .burst {
+      position: absolute; bottom: -8vh; font-size: 6vh;
+      animation: float-up var(--dur) ease-out forwards;
+    }
+    @keyframes float-up {
+      0% { transform: translate(0, 0) scale(.6); opacity: 0; }
+      10% { opacity: 1; }
+      100% { transform: translate(var(--drift), -105vh) scale(1.2); opacity: 0; }
+    }
+    .bottom { font-size: 1.8vh; color: var(--muted); display: flex; justify-content: space-between; align-items: end; gap: 3vw; }
+    .meters { display: flex; gap: 3vw; flex: 1; }
+    .meter { flex: 1; max-width: 22vw; font-size: 2.2vh; }
+    .meter b { color: var(--text); }
+    .meter-track { height: 1.4vh; background: rgba(148, 163, 184, .12); border-radius: 999px; overflow: hidden; margin-top: .6vh; }
+    .meter-fill { height: 100%; background: var(--accent); transition: width 1.2s ease-out; }
+    .meter-fill.low { background: #f43f5e; }
+    .changes { margin-top: 2vh; font-size: 3vh; opacity: 0; transition: opacity .8s ease-out 1.4s; }
+    .reveal.on .changes { opacity: 1; }
+    .changes .up { color: #22c55e; margin-right: 2vw; }
+    .changes .down { color: #f43f5e; margin-right: 2vw; }
+    .ending {
+      position: fixed; inset: 0; display: none; place-items: center; text-align: center;
+      background: rgba(2, 6, 23, .92); padding: 0 10vw;
+    }
+    .ending.on { display: grid; animation: rise .8s ease-out; }
+    .ending h1 { color: #f43f5e; font-size: 9vh; }
+    .notice {
+      position: fixed; top: 0; left: 0; right: 0; display: none;
+      background: #facc15; color: #020617; text-align: center;
+      font-size: 2.6vh; font-weight: 600; padding: 1.2vh 4vw;
+    }
+    .notice.on { display: block; }
+    .ending p { font-size: 3.6vh; color: var(--muted); }
+    @keyframes rise {
+      from { opacity: 0; transform: translateY(3vh); }
+      to { opacity: 1; transform: none; }
     }
   </style>
 </head>
 <body>
-  <h1>Админ — Корпокликер</h1>
-  <div class="panel">
-    <button onclick="showAnswer()">Показать ответ</button>
-    <button onclick="nextSituation()">Дальше</button>
-    <button onclick="resetCounts()">Сброс</button>
-    <pre id="out">Нажми “Показать ответ”, чтобы отдать его игрокам</pre>
+  <div class="top">
+    <div class="join">
+      <img src="/qr.svg" alt="QR" />
+      <div>
+        <div>Заходите: <b id="join-url"></b></div>
+        <div id="code-row" style="display:none;">или код на <b id="join-page"></b>: <b class="code" id="join-code"></b></div>
+      </div>
+    </div>
+    <div class="voters"><b id="players">0</b> на связи · <b id="clicks">0</b> <span id="clicks-label">кликов</span></div>
+  </div>
+
+  <div class="stage">
+    <h1 id="title">Загрузка…</h1>
+    <p class="desc" id="desc"></p>
+
+    <div class="reveal" id="reveal">
+      <div class="bars-caption" id="bars-caption"></div>
+      <div class="bars">
+        <div class="bar"><span>🗯 Врать</span><div class="track"><div class="fill" id="fill-0"></div></div><span class="num" id="num-0"></span></div>
+        <div class="bar"><span>⏱ Отложить</span><div class="track"><div class="fill" id="fill-1"></div></div><span class="num" id="num-1"></span></div>
+        <div class="bar"><span>🧊 Заморозить</span><div class="track"><div class="fill" id="fill-2"></div></div><span class="num" id="num-2"></span></div>
+      </div>
+      <div class="drumroll" id="drumroll"></div>
+      <div class="answer" id="answer"></div>
+      <div class="changes" id="changes"></div>
+      <div class="team-answers" id="team-answers"></div>
+      <div class="comparison" id="comparison"></div>
+      <ol class="leaders" id="leaders"></ol>
+    </div>
+  </div>
+
+  <div class="bottom">
+    <div class="meters" id="meters"></div>
+    <div class="discussion" id="discussion"></div>
+    <div>Корпокликер</div>
   </div>
+  <div class="ending" id="ending">
+    <div>
+      <h1 id="ending-title"></h1>
+      <p id="ending-text"></p>
+    </div>
+  </div>
+  <div class="bursts" id="bursts"></div>
+  <div class="notice" id="notice"></div>
 
   <script>
-    async function showAnswer() {
-      const r = await fetch('/admin/show');
-      const d = await r.json();
-      document.getElementById('out').innerText =
-        'Ситуация: ' + d.situation_title +
-        '\n\nОтвет:\n' + d.answer +
-        '\n\nКлики (Врать, Отложить, Заморозить): ' + d.counts.join(', ');
+    fetch('/api/join').then(r => r.json()).then(d => {
+      document.getElementById('join-url').innerText = d.url;
+      if (d.code) {
+        document.getElementById('join-page').innerText = location.host + '/join';
+        document.getElementById('join-code').innerText = d.code;
+        document.getElementById('code-row').style.display = 'block';
+      }
+    });
+    let shownVersion = null;
+    let shownStage = null;
+    let countdownTimer = null;
+    let comparisonVersion = null;
+    let discussionEnd = null;
+    let discussionOver = false;
+    const PAIR_ICONS = {lie: '🗯', delay: '⏱', freeze: '🧊'};
+
+    function renderComparison(c) {
+      const box = document.getElementById('comparison');
+      box.innerHTML = '';
+      comparisonVersion = c ? c.version : null;
+      for (const [i, e] of (c ? c.entries : []).entries()) {
+        const el = document.createElement('div');
+        el.className = 'compare' + (e.chosen ? ' chosen' : '');
+        el.style.animationDelay = (i * .4) + 's';
+        const head = document.createElement('b');
+        const pair = document.createElement('span');
+        pair.innerText = e.pair.map(p => PAIR_ICONS[p]).join(' + ') + (e.chosen ? ' ★' : '');
+        const share = document.createElement('span');
+        share.innerText = e.share + '%';
+        head.appendChild(pair);
+        head.appendChild(share);
+        el.appendChild(head);
+        for (const a of e.answers) {
+          const p = document.createElement('p');
+          p.innerText = a;
+          el.appendChild(p);
+        }
+        box.appendChild(el);
+      }
     }
-    async function nextSituation() {
-      await fetch('/admin/next', {method:'POST'});
-      document.getElementById('out').innerText = 'Переключено на следующую ситуацию, клики сброшены.';
+
+    function hideReveal() {
+      shownVersion = null;
+      shownStage = null;
+      clearInterval(countdownTimer);
+      renderComparison(null);
+      const box = document.getElementById('reveal');
+      box.classList.remove('on');
+      for (let i = 0; i < 3; i++) document.getElementById('fill-' + i).style.width = '0';
     }
-    async function resetCounts() {
-      await fetch('/admin/reset', {method:'POST'});
-      document.getElementById('out').innerText = 'Клики и показанный ответ сброшены.';
+
+    // этап показа приходит с сервера; отсчёт между опросами тикаем сами
+    function applyStage(r) {
+      const box = document.getElementById('reveal');
+      const stage = r.reveal.stage;
+      if (stage !== 'answer') box.classList.add('staged');
+      box.classList.toggle('waiting', stage !== 'answer');
+      box.classList.toggle('countdown', stage === 'countdown');
+      clearInterval(countdownTimer);
+      if (stage === 'countdown') {
+        const end = Date.now() + r.reveal.left_ms;
+        const tick = () => {
+          const left = Math.ceil((end - Date.now()) / 1000);
+          document.getElementById('drumroll').innerText = left > 0 ? left : '🥁';
+        };
+        tick();
+        countdownTimer = setInterval(tick, 200);
+      }
+      shownStage = stage;
+    }
+
+    function showReveal(r) {
+      const box = document.getElementById('reveal');
+      const total = r.counts.reduce((a, b) => a + b, 0) || 1;
+      box.classList.remove('on', 'staged');
+      for (let i = 0; i < 3; i++) {
+        document.getElementById('fill-' + i).style.width = '0';
+        document.getElementById('num-' + i).innerText = r.counts[i];
+      }
+      fillDetails(r);
+      applyStage(r);
+      // перезапуск анимации: сначала кадр с нулевыми полосками, потом целевые
+      void box.offsetWidth;
+      box.classList.add('on');
+      requestAnimationFrame(() => {
+        for (let i = 0; i < 3; i++) {
+          document.getElementById('fill-' + i).style.width = (r.counts[i] / total * 100) + '%';
+        }
+      });
+    }
+
+    // всё, что идёт после полосок: до последнего этапа сервер присылает это пустым
+    function fillDetails(r) {
+      document.getElementById('answer').innerText = r.answer;
+      document.getElementById('bars-caption').innerText = r.ranking
+        ? (r.ranking.method === 'borda' ? 'Очки Борда' : 'Попарные победы') + ' · бюллетеней: ' + r.ranking.ballots
+        : '';
+      const changes = document.getElementById('changes');
+      changes.innerHTML = '';
+      for (const c of r.changes || []) {
+        const el = document.createElement('span');
+        el.className = c.delta > 0 ? 'up' : 'down';
+        el.innerText = c.label + ' ' + (c.delta > 0 ? '+' : '') + c.delta;
+        changes.appendChild(el);
+      }
+      const teamBox = document.getElementById('team-answers');
+      teamBox.innerHTML = '';
+      for (const t of r.teams || []) {
+        const el = document.createElement('div');
+        el.className = 'team-answer';
+        const name = document.createElement('b');
+        name.innerText = t.name;
+        const counts = document.createElement('small');
+        counts.innerText = '🗯 ' + t.counts[0] + ' · ⏱ ' + t.counts[1] + ' · 🧊 ' + t.counts[2];
+        el.appendChild(name);
+        el.appendChild(document.createTextNode(t.answer));
+        el.appendChild(counts);
+        teamBox.appendChild(el);
+      }
+      const leaders = document.getElementById('leaders');
+      leaders.innerHTML = '';
+      for (const e of r.leaderboard || []) {
+        const li = document.createElement('li');
+        const name = document.createElement('b');
+        name.innerText = e.name;
+        li.appendChild(name);
+        li.appendChild(document.createTextNode(' — ' + e.points));
+        leaders.appendChild(li);
+      }
+    }
+
+    function tickDiscussion() {
+      const el = document.getElementById('discussion');
+      el.classList.toggle('over', discussionOver);
+      if (discussionEnd === null) {
+        el.innerText = '';
+        return;
+      }
+      const secs = Math.max(0, Math.ceil((discussionEnd - Date.now()) / 1000));
+      el.innerText = discussionOver || secs === 0
+        ? '💬 Время!'
+        : '💬 ' + Math.floor(secs / 60) + ':' + String(secs % 60).padStart(2, '0');
+    }
+    setInterval(tickDiscussion, 250);
+
+    function renderMeters(list) {
+      const box = document.getElementById('meters');
+      box.innerHTML = '';
+      for (const m of list) {
+        const pct = Math.round((m.value - m.min) * 100 / Math.max(1, m.max - m.min));
+        const el = document.createElement('div');
+        el.className = 'meter';
+        const label = document.createElement('b');
+        label.innerText = m.label;
+        el.appendChild(label);
+        el.appendChild(document.createTextNode(' ' + m.value));
+        const track = document.createElement('div');
+        track.className = 'meter-track';
+        const fill = document.createElement('div');
+        fill.className = 'meter-fill' + (pct <= 20 ? ' low' : '');
+        fill.style.width = pct + '%';
+        track.appendChild(fill);
+        el.appendChild(track);
+        box.appendChild(el);
+      }
+    }
+
+    function renderEnding(ending) {
+      document.getElementById('ending').classList.toggle('on', !!ending);
+      if (!ending) return;
+      document.getElementById('ending-title').innerText = ending.title;
+      document.getElementById('ending-text').innerText = ending.text;
+    }
+
+    async function pollLoop() {
+      try {
+        const res = await fetch('/api/present');
+        const d = await res.json();
+        document.getElementById('title').innerText = d.title;
+        document.getElementById('desc').innerText = d.description;
+        document.getElementById('players').innerText = d.players;
+        const ranked = d.voting !== 'clicks';
+        document.getElementById('clicks').innerText = ranked ? d.ballots : d.clicks;
+        document.getElementById('clicks-label').innerText = ranked ? 'бюллетеней' : 'кликов';
+        renderMeters(d.meters);
+        renderEnding(d.ending);
+        const notice = document.getElementById('notice');
+        notice.innerText = d.notice || '';
+        notice.classList.toggle('on', !!d.notice);
+        // таймер тикает локально, опрос только подводит часы
+        const talk = d.discussion && !d.discussion.pending ? d.discussion : null;
+        discussionEnd = talk ? Date.now() + talk.left_ms : null;
+        discussionOver = !!(talk && talk.over);
+        tickDiscussion();
+        if (!d.result) {
+          if (shownVersion !== null) hideReveal();
+        } else if (d.result.version !== shownVersion) {
+          shownVersion = d.result.version;
+          showReveal(d.result);
+        } else if (d.result.reveal.stage !== shownStage) {
+          fillDetails(d.result);
+          applyStage(d.result);
+        }
+        const comparison = d.result ? d.result.comparison : null;
+        if ((comparison ? comparison.version : null) !== comparisonVersion) renderComparison(comparison);
+      } catch (e) {
+        // сервер мог перезапускаться — просто пробуем ещё
+      } finally {
+        setTimeout(pollLoop, 1000);
+      }
+    }
+
+    pollLoop();
+
+    // ---- эмодзи: сервер копит по секундам, мы раскладываем секунду на всплывашки ----
+    const GLYPHS = ['🤦', '😂', '🔥'];
+    const MAX_PER_SECOND = 40; // чтобы шквал не положил проектор
+    let emojiSince = null;
+
+    function spawn(glyph, delayMs) {
+      setTimeout(() => {
+        const el = document.createElement('div');
+        el.className = 'burst';
+        el.innerText = glyph;
+        el.style.left = (5 + Math.random() * 90) + 'vw';
+        el.style.setProperty('--drift', (Math.random() * 20 - 10) + 'vw');
+        el.style.setProperty('--dur', (2.5 + Math.random() * 1.5) + 's');
+        document.getElementById('bursts').appendChild(el);
+        el.addEventListener('animationend', () => el.remove());
+      }, delayMs);
+    }
+
+    async function emojiLoop() {
+      try {
+        const q = emojiSince === null ? '' : '?since=' + emojiSince;
+        const d = await (await fetch('/api/emoji' + q)).json();
+        // первый запрос только синхронизирует часы — старые секунды не проигрываем
+        if (emojiSince !== null) {
+          for (const b of d.buckets) {
+            const total = b.counts.reduce((a, c) => a + c, 0);
+            const scale = Math.min(1, MAX_PER_SECOND / total);
+            b.counts.forEach((n, i) => {
+              for (let k = 0; k < Math.round(n * scale); k++) spawn(GLYPHS[i], Math.random() * 1000);
+            });
+          }
+        }
+        emojiSince = d.buckets.length ? d.buckets[d.buckets.length - 1].second : Math.max(0, d.now - 1);
+      } catch (e) {
+        // пропустим секунду-другую, не страшно
+      } finally {
+        setTimeout(emojiLoop, 1000);
+      }
     }
+
+    emojiLoop();
   </script>
 </body>
 </html>
 "#;
 
+// ===================== Проверка колоды =====================
+
+/// Всё, что можно поймать до старта игры, ловим здесь, а не на показе ответа.
+fn validate_deck(situations: &[Situation]) -> Result<(), String> {
+    if situations.is_empty() {
+        return Err("deck has no situations".to_string());
+    }
+    for (i, s) in situations.iter().enumerate() {
+        let at = |what: &str, e: template::TemplateError| {
+            format!("situation #{i} \"{}\", {what}: {e}", s.title)
+        };
+        template::validate(&s.description).map_err(|e| at("description", e))?;
+        if let Some(WinnerRule::Override { pair: (a, b) }) = s.winner {
+            if a == b {
+                return Err(format!(
+                    "situation #{i} \"{}\", winner override needs two different reactions",
+                    s.title
+                ));
+            }
+        }
+        for (n, rule) in s.rules.iter().enumerate() {
+            if let Some(e) = rule.error() {
+                return Err(format!(
+                    "situation #{i} \"{}\", rule {n} \"{}\": {e}",
+                    s.title, rule.when
+                ));
+            }
+            template::validate(&rule.answer).map_err(|e| at(&format!("rule {n} answer"), e))?;
+        }
+        if s.script.duration_secs == Some(0) {
+            return Err(format!(
+                "situation #{i} \"{}\", suggested duration must be positive",
+                s.title
+            ));
+        }
+        for (pair, pool) in &s.answers {
+            for (v, text) in pool.iter().enumerate() {
+                template::validate(text)
+                    .map_err(|e| at(&format!("answer {pair:?} variant {v}"), e))?;
+            }
+        }
+    }
+    branching::validate(situations)
+}
+
 // ===================== Ситуации =====================
 
+#[allow(clippy::vec_init_then_push)]
 fn build_situations() -> Vec<Situation> {
     let mut v = Vec::new();
 
@@ -520,6 +3960,17 @@ fn build_situations() -> Vec<Situation> {
             "Там образовался важный созвон, пришлось подвигать. Не закапываемся, идём по текущему расписанию.",
             "Время ещё финалится на уровне выше. Пока работаем так, без обсуждения. (что бы это не значило...)",
         ),
+        effects: make_effects(
+            &[("trust", -5), ("morale", 5)],
+            &[("trust", -10)],
+            &[("morale", -5)],
+        ),
+        script: make_script(
+            "Разогрев: объясните, что жать можно сколько угодно раз, считаются все клики. Не затягивайте — зал ещё стесняется.",
+            &["Кому переносили ретро больше двух раз подряд?"],
+            2,
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -530,6 +3981,12 @@ fn build_situations() -> Vec<Situation> {
             "Так и планировалось, просто вы пока не видите полный контекст. Берём то, что есть.",
             "Это сейчас решается выше. Как скажут - так и возьмём, возвращаться не будем.",
         ),
+        effects: make_effects(
+            &[("trust", -5), ("morale", -5)],
+            &[("trust", -10), ("morale", 5)],
+            &[("morale", -10)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -540,6 +3997,12 @@ fn build_situations() -> Vec<Situation> {
             "Это практически финальная версия, но у нас вчера был напряжённый релиз, поэтому не всё прошло гладко.",
             "Сейчас не до этого, у нас важный хотфикс. Как стабилизируемся - оформим и вернёмся.",
         ),
+        effects: make_effects(
+            &[("trust", -5), ("budget", -5)],
+            &[("trust", -10)],
+            &[("morale", -5), ("budget", 5)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -550,6 +4013,12 @@ fn build_situations() -> Vec<Situation> {
             "Это был изначально контрольный созвон. Просто отметимся и дальше.",
             "Так решили сверху. Проводим и не обсуждаем.",
         ),
+        effects: make_effects(
+            &[("trust", -5), ("morale", -5)],
+            &[("trust", -5), ("budget", 5)],
+            &[("morale", -10)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -560,17 +4029,50 @@ fn build_situations() -> Vec<Situation> {
             "Документация есть в рабочем виде. Сейчас это вторично.",
             "Фокус не на этом. Как будут ресурсы - сделаем.",
         ),
+        effects: make_effects(
+            &[("trust", -5), ("budget", 5)],
+            &[("trust", -10), ("budget", 5)],
+            &[("morale", -5), ("budget", 10)],
+        ),
+        ..Default::default()
     });
 
     // ===== БЛОК 2: банальные =====
     v.push(Situation {
         title: "Когда будет зарплата за этот месяц?".to_string(),
         description: "Самый ожидаемый вопрос...".to_string(),
-        answers: make_answers(
-            "Платёж уже ушёл, деньги в пути. Если до конца недели не придут - дёрнем ещё раз.",
-            "Она заложена, просто сейчас задержка на стороне бухгалтерии или банка. Не останавливаемся, работаем.",
-            "Точной даты сейчас не дадим. Как только будет финал - сообщим единым сообщением.",
+        answers: make_answer_pools(
+            &[
+                "Платёж уже ушёл, деньги в пути. Если до конца недели не придут - дёрнем ещё раз.",
+                "Бухгалтерия уже всё отправила, банк просто проверяет. Чуть позже пришлю скрин платёжки.",
+            ],
+            &[
+                "Она заложена, просто сейчас задержка на стороне бухгалтерии или банка. Не останавливаемся, работаем.",
+                "Деньги есть, это чисто технический момент. Давайте не отвлекаться от спринта.",
+            ],
+            &[
+                "Точной даты сейчас не дадим. Как только будет финал - сообщим единым сообщением.",
+                "Ждём подтверждения сверху. Вопрос пока закрываем, апдейт будет в общем канале.",
+            ],
         ),
+        effects: make_effects(
+            &[("trust", -15), ("morale", 5)],
+            &[("trust", -20), ("morale", 10)],
+            &[("morale", -15), ("budget", 5)],
+        ),
+        rules: make_rules(&[(
+            "freeze > 70%",
+            "Зал почти единогласно заморозил вопрос о зарплате ({freeze_pct}%). Видимо, её и правда не будет.",
+        )]),
+        script: make_script(
+            "Больная тема — без шуток над теми, у кого задержка прямо сейчас. Если зал затих, спросите, какой ответ они слышали вживую.",
+            &[
+                "Какой из вариантов вы слышали на самом деле?",
+                "Что бы вы хотели услышать вместо этого?",
+            ],
+            5,
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -581,6 +4083,12 @@ fn build_situations() -> Vec<Situation> {
             "Информация была, но в рабочем виде. Сейчас не копаемся, идём дальше.",
             "Коммуникацию улучшим. Пока фиксируем, что так случилось.",
         ),
+        effects: make_effects(
+            &[("trust", -5), ("morale", 5)],
+            &[("trust", -10)],
+            &[("trust", 5), ("morale", -5)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -591,6 +4099,12 @@ fn build_situations() -> Vec<Situation> {
             "Корпоратив заложен, просто сейчас не время раскрывать детали. Не спойлерим.",
             "Сначала стабилизируемся по выплатам, потом развлечения. Тему пока откладываем.",
         ),
+        effects: make_effects(
+            &[("morale", 5), ("budget", -5)],
+            &[("trust", -5), ("morale", 5)],
+            &[("morale", -10), ("budget", 10)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -601,6 +4115,12 @@ fn build_situations() -> Vec<Situation> {
             "Мерч - часть HR-стратегии, он не отменён. Сейчас не отвлекаемся от задач.",
             "Пока приоритет не на этом. Вернёмся к мерчу, когда будет окно.",
         ),
+        effects: make_effects(
+            &[("morale", 5), ("budget", -10)],
+            &[("trust", -5)],
+            &[("morale", -5), ("budget", 5)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -611,17 +4131,70 @@ fn build_situations() -> Vec<Situation> {
             "Набор - часть стратегии роста. Не смешиваем это с выплатами.",
             "Этим занимается другой отдел. В общем финобновлении будет ответ.",
         ),
+        effects: make_effects(
+            &[("trust", -10), ("budget", -5)],
+            &[("trust", -10), ("morale", -5)],
+            &[("trust", -5), ("morale", -5)],
+        ),
+        ..Default::default()
     });
 
     // ===== БЛОК 3: острые =====
     v.push(Situation {
         title: "Компания вообще жива? Нас не закрывают?".to_string(),
-        description: "Панический вопрос!.!".to_string(),
+        description: "Панический вопрос!.! Спрашивают уже {player_count} человек.".to_string(),
         answers: make_answers(
             "Компания в норме, мы просто в перестройке. Чуть позже покажем все цифры.",
             "У нас всё под контролем, вы видите только часть. Не паникуем, работаем.",
-            "Эту тему сейчас не поднимаем. Будет официальный апдейт - получите.",
+            "Эту тему сейчас не поднимаем. Будет официальный апдейт - получите. (Так ответили {total} человек из зала.)",
+        ),
+        // соврали и заморозили — кто-то из зала не выдерживает
+        branches: HashMap::from([(
+            ordered_tuple(Reaction::Lie, Reaction::Freeze),
+            "whistleblower".to_string(),
+        )]),
+        effects: make_effects(
+            &[("trust", -10), ("morale", 5)],
+            &[("trust", -20)],
+            &[("trust", -5), ("morale", -15)],
+        ),
+        rules: make_rules(&[
+            (
+                "total < 3",
+                "На этот вопрос никто не решился ответить. Это и есть ответ.",
+            ),
+            (
+                "lie within 10% of delay and freeze < 20%",
+                "Руководство разделилось: половина уже врёт, половина ещё тянет время. Официальная позиция будет, когда договорятся между собой.",
+            ),
+        ]),
+        script: make_script(
+            "Предупредите: если зал выберет «врать + заморозить», кто-то сольёт выписку — будет внеочередная ситуация.",
+            &[
+                "Что для вас первый признак, что компанию закрывают?",
+                "Кто бы первым узнал об этом в вашей команде?",
+            ],
+            4,
+        ),
+        ..Default::default()
+    });
+
+    v.push(Situation {
+        id: Some("whistleblower".to_string()),
+        branch_only: true,
+        title: "В общий чат слили выписку со счёта компании".to_string(),
+        description: "Кто-то не выдержал после прошлого ответа...".to_string(),
+        answers: make_answers(
+            "Это старая выписка, там не всё учтено. Позже покажем актуальную.",
+            "Это фейк. Не распространяем, работаем дальше.",
+            "Утечку разбирает служба безопасности. Тему не обсуждаем до окончания проверки.",
+        ),
+        effects: make_effects(
+            &[("trust", -15)],
+            &[("trust", -25), ("morale", -10)],
+            &[("morale", -10), ("budget", -10)],
         ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -632,6 +4205,12 @@ fn build_situations() -> Vec<Situation> {
             "Мы говорили про те конкретные случаи, этот - другой. Не смешиваем.",
             "Сейчас не копаемся в формулировках. Важно, что двигаемся к нормальному циклу.",
         ),
+        effects: make_effects(
+            &[("trust", -15)],
+            &[("trust", -15), ("morale", 5)],
+            &[("morale", -10)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -642,6 +4221,19 @@ fn build_situations() -> Vec<Situation> {
             "ИИ - это доп-инструмент, а не замена. Сейчас не уходим в эту тему.",
             "Это не приоритет сейчас. Как будет стратегия по AI - презентуем.",
         ),
+        // отвечает как будто уже ИИ — невпопад, самым непопулярным
+        winner: Some(WinnerRule::LeastPopular),
+        script: make_script(
+            "Здесь пара выбирается назло залу — не выдавайте заранее, пусть удивятся.",
+            &["Какую часть вашей работы вы бы отдали ИИ прямо завтра?"],
+            3,
+        ),
+        effects: make_effects(
+            &[("trust", -5), ("budget", -5)],
+            &[("trust", -5)],
+            &[("morale", -10), ("budget", 5)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -652,6 +4244,12 @@ fn build_situations() -> Vec<Situation> {
             "Это под конкретные задачи. Сейчас не будем сравнивать железо.",
             "Сначала закрываем рабочие вопросы. Обновление техники обсудим отдельно.",
         ),
+        effects: make_effects(
+            &[("morale", 5), ("budget", -10)],
+            &[("trust", -10)],
+            &[("morale", -10)],
+        ),
+        ..Default::default()
     });
 
     v.push(Situation {
@@ -662,19 +4260,133 @@ fn build_situations() -> Vec<Situation> {
             "Цифры положительные, просто они внутренняя инфа. Сейчас не тот формат.",
             "Финансовая инфа будет в официальном канале. Пока тему закрываем.",
         ),
+        effects: make_effects(
+            &[("trust", -10), ("morale", 5)],
+            &[("trust", -20)],
+            &[("trust", -10), ("morale", -5)],
+        ),
+        ..Default::default()
     });
 
     v
 }
 
+// шкалы компании для этой колоды
+fn build_meters() -> meters::Config {
+    let meter = |id: &str, label: &str| MeterDef {
+        id: id.to_string(),
+        label: label.to_string(),
+        start: 50,
+        min: 0,
+        max: 100,
+    };
+    let ending = |meter: &str, bound: Bound, title: &str, text: &str| Ending {
+        meter: meter.to_string(),
+        bound,
+        title: title.to_string(),
+        text: text.to_string(),
+    };
+    meters::Config {
+        meters: vec![
+            meter("trust", "Доверие"),
+            meter("morale", "Мораль"),
+            meter("budget", "Бюджет"),
+        ],
+        endings: vec![
+            ending(
+                "trust",
+                Bound::AtMost(0),
+                "Компания развалилась",
+                "Словам руководства больше никто не верит. Команда ушла к конкурентам в полном составе.",
+            ),
+            ending(
+                "morale",
+                Bound::AtMost(0),
+                "Все выгорели",
+                "Созвоны идут, камеры выключены, на той стороне давно никого нет.",
+            ),
+            ending(
+                "budget",
+                Bound::AtMost(0),
+                "Деньги кончились",
+                "Ответы были вдохновляющие, но платить за них оказалось нечем.",
+            ),
+            ending(
+                "budget",
+                Bound::AtLeast(100),
+                "Сэкономили на всём",
+                "Бюджет сошёлся идеально. Правда, тратить его уже не на кого.",
+            ),
+        ],
+    }
+}
+
 fn make_answers(
     lie_delay: &str,
     lie_freeze: &str,
     delay_freeze: &str,
-) -> HashMap<(Reaction, Reaction), String> {
+) -> HashMap<(Reaction, Reaction), Vec<String>> {
+    make_answer_pools(&[lie_delay], &[lie_freeze], &[delay_freeze])
+}
+
+// то же, но с несколькими вариантами ответа на пару
+fn make_answer_pools(
+    lie_delay: &[&str],
+    lie_freeze: &[&str],
+    delay_freeze: &[&str],
+) -> HashMap<(Reaction, Reaction), Vec<String>> {
+    let pool = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
     let mut m = HashMap::new();
-    m.insert(ordered_tuple(Reaction::Lie, Reaction::Delay), lie_delay.to_string());
-    m.insert(ordered_tuple(Reaction::Lie, Reaction::Freeze), lie_freeze.to_string());
-    m.insert(ordered_tuple(Reaction::Delay, Reaction::Freeze), delay_freeze.to_string());
+    m.insert(
+        ordered_tuple(Reaction::Lie, Reaction::Delay),
+        pool(lie_delay),
+    );
+    m.insert(
+        ordered_tuple(Reaction::Lie, Reaction::Freeze),
+        pool(lie_freeze),
+    );
+    m.insert(
+        ordered_tuple(Reaction::Delay, Reaction::Freeze),
+        pool(delay_freeze),
+    );
     m
 }
+
+// шпаргалка ведущего: заметки, вопросы к залу и сколько минут на ситуацию
+fn make_script(notes: &str, prompts: &[&str], minutes: u64) -> Script {
+    Script {
+        notes: notes.to_string(),
+        prompts: prompts.iter().map(|p| p.to_string()).collect(),
+        duration_secs: Some(minutes * 60),
+    }
+}
+
+// правила «условие -> ответ», проверяются по порядку
+fn make_rules(list: &[(&str, &str)]) -> Vec<Rule> {
+    list.iter()
+        .map(|(when, answer)| Rule::new(when, answer))
+        .collect()
+}
+
+// сдвиги шкал по парам, в том же порядке, что и make_answers
+fn make_effects(
+    lie_delay: &[(&str, i64)],
+    lie_freeze: &[(&str, i64)],
+    delay_freeze: &[(&str, i64)],
+) -> HashMap<(Reaction, Reaction), Vec<(String, i64)>> {
+    let list = |v: &[(&str, i64)]| v.iter().map(|(id, d)| (id.to_string(), *d)).collect();
+    HashMap::from([
+        (
+            ordered_tuple(Reaction::Lie, Reaction::Delay),
+            list(lie_delay),
+        ),
+        (
+            ordered_tuple(Reaction::Lie, Reaction::Freeze),
+            list(lie_freeze),
+        ),
+        (
+            ordered_tuple(Reaction::Delay, Reaction::Freeze),
+            list(delay_freeze),
+        ),
+    ])
+}
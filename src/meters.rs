@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Situation;
+
+// ===================== Шкалы компании =====================
+//
+// Каждая пара ответа двигает шкалы (доверие, мораль, бюджет). Значения копятся
+// всю сессию; как только шкала попала под условие концовки — игра окончена.
+
+#[derive(Clone, Debug)]
+pub struct MeterDef {
+    pub id: String,
+    pub label: String,
+    pub start: i64,
+    pub min: i64,
+    pub max: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    AtMost(i64),
+    AtLeast(i64),
+}
+
+impl Bound {
+    fn holds(self, value: i64) -> bool {
+        match self {
+            Bound::AtMost(v) => value <= v,
+            Bound::AtLeast(v) => value >= v,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Ending {
+    pub meter: String,
+    pub bound: Bound,
+    pub title: String,
+    pub text: String,
+}
+
+/// Шкалы и концовки колоды. Пустой список шкал — режим выключен.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub meters: Vec<MeterDef>,
+    // проверяются по порядку, срабатывает первая подходящая
+    pub endings: Vec<Ending>,
+}
+
+/// Насколько сдвинулась шкала после показа ответа.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    pub meter: String,
+    pub label: String,
+    pub delta: i64,
+}
+
+#[derive(Serialize)]
+pub struct MeterView {
+    pub id: String,
+    pub label: String,
+    pub value: i64,
+    pub min: i64,
+    pub max: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndingView {
+    pub title: String,
+    pub text: String,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Meters {
+    #[serde(skip)]
+    pub config: Config,
+    values: Vec<i64>,
+    // значения до первого показа ответа в этой ситуации: повторный показ
+    // пересчитывает от них, а не накладывает эффект второй раз
+    #[serde(default)]
+    round_start: Option<Vec<i64>>,
+}
+
+impl Meters {
+    pub fn new(config: Config) -> Self {
+        let values = config.meters.iter().map(|m| m.start).collect();
+        Meters {
+            config,
+            values,
+            round_start: None,
+        }
+    }
+
+    /// Применяет эффекты показанного ответа и возвращает фактические сдвиги
+    /// за ситуацию (после упора в min/max).
+    pub fn apply(&mut self, effects: &[(String, i64)]) -> Vec<Change> {
+        let start = self
+            .round_start
+            .get_or_insert_with(|| self.values.clone())
+            .clone();
+        self.values = start.clone();
+        for (id, delta) in effects {
+            if let Some(i) = self.config.meters.iter().position(|m| &m.id == id) {
+                let m = &self.config.meters[i];
+                self.values[i] = (self.values[i] + delta).clamp(m.min, m.max);
+            }
+        }
+        self.config
+            .meters
+            .iter()
+            .zip(self.values.iter().zip(&start))
+            .filter(|(_, (now, was))| now != was)
+            .map(|(m, (now, was))| Change {
+                meter: m.id.clone(),
+                label: m.label.clone(),
+                delta: now - was,
+            })
+            .collect()
+    }
+
+    /// Новая ситуация — эффекты прошлой зафиксированы.
+    pub fn new_round(&mut self) {
+        self.round_start = None;
+    }
+
+    /// Админ сбросил голосование: откатываем эффект показанного ответа.
+    pub fn undo_round(&mut self) {
+        if let Some(start) = self.round_start.take() {
+            self.values = start;
+        }
+    }
+
+    /// Новая игра: все шкалы на старт.
+    pub fn restart(&mut self) {
+        *self = Meters::new(std::mem::take(&mut self.config));
+    }
+
+    pub fn value(&self, id: &str) -> Option<i64> {
+        let i = self.config.meters.iter().position(|m| m.id == id)?;
+        self.values.get(i).copied()
+    }
+
+    pub fn view(&self) -> Vec<MeterView> {
+        self.config
+            .meters
+            .iter()
+            .zip(&self.values)
+            .map(|(m, &value)| MeterView {
+                id: m.id.clone(),
+                label: m.label.clone(),
+                value,
+                min: m.min,
+                max: m.max,
+            })
+            .collect()
+    }
+
+    /// Шкалы, какими они были до показа ответа в этой ситуации.
+    pub fn before_round(&self) -> Meters {
+        let mut before = self.clone();
+        if let Some(start) = &self.round_start {
+            before.values = start.clone();
+        }
+        before
+    }
+
+    /// Концовка, если игра окончена.
+    pub fn ending(&self) -> Option<EndingView> {
+        self.config
+            .endings
+            .iter()
+            .find(|e| self.value(&e.meter).is_some_and(|v| e.bound.holds(v)))
+            .map(|e| EndingView {
+                title: e.title.clone(),
+                text: e.text.clone(),
+            })
+    }
+
+    /// Восстановление из файла: значения берём, только если набор шкал тот же.
+    pub fn restore(&mut self, saved: Meters) {
+        if saved.values.len() == self.config.meters.len()
+            && saved
+                .round_start
+                .as_ref()
+                .is_none_or(|r| r.len() == saved.values.len())
+        {
+            self.values = saved.values;
+            self.round_start = saved.round_start;
+        }
+    }
+}
+
+/// Проверка при загрузке колоды: все ссылки на шкалы существуют, границы в порядке.
+pub fn validate(config: &Config, situations: &[Situation]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for m in &config.meters {
+        if !ids.insert(m.id.as_str()) {
+            return Err(format!("duplicate meter \"{}\"", m.id));
+        }
+        if !(m.min <= m.start && m.start <= m.max) {
+            return Err(format!(
+                "meter \"{}\": start {} is outside {}..={}",
+                m.id, m.start, m.min, m.max
+            ));
+        }
+    }
+    for e in &config.endings {
+        if !ids.contains(e.meter.as_str()) {
+            return Err(format!(
+                "ending \"{}\" refers to unknown meter \"{}\"",
+                e.title, e.meter
+            ));
+        }
+    }
+    for (i, s) in situations.iter().enumerate() {
+        for (pair, effects) in &s.effects {
+            if !s.answers.is_empty() && !s.answers.contains_key(pair) {
+                return Err(format!(
+                    "situation #{i} \"{}\": effects for {pair:?} without an answer",
+                    s.title
+                ));
+            }
+            for (id, _) in effects {
+                if !ids.contains(id.as_str()) {
+                    return Err(format!(
+                        "situation #{i} \"{}\": effect {pair:?} refers to unknown meter \"{id}\"",
+                        s.title
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+// ===================== Метрики (Prometheus) =====================
+
+// границы бакетов гистограммы задержек, в секундах
+const LATENCY_BUCKETS: [f64; 9] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0];
+
+// игрок считается подключённым, если опрашивал сервер за последние N секунд
+const PLAYER_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        for (i, le) in LATENCY_BUCKETS.iter().enumerate() {
+            if secs <= *le {
+                self.buckets[i] += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Metrics {
+    clicks: [u64; 3], // [lie, delay, freeze], за всё время работы
+    clicks_rejected: u64,
+    reveals: u64,
+    // ключ: шаблон маршрута ("/api/click"), BTreeMap — чтобы вывод был стабильным
+    latency: BTreeMap<String, Histogram>,
+    players_seen: HashMap<IpAddr, Instant>,
+}
+
+impl Metrics {
+    pub fn click(&mut self, idx: usize) {
+        self.clicks[idx] += 1;
+    }
+
+    pub fn click_rejected(&mut self) {
+        self.clicks_rejected += 1;
+    }
+
+    pub fn reveal(&mut self) {
+        self.reveals += 1;
+    }
+
+    pub fn observe_latency(&mut self, route: &str, elapsed: Duration) {
+        self.latency
+            .entry(route.to_string())
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn player_seen(&mut self, ip: IpAddr) {
+        let now = Instant::now();
+        self.players_seen.insert(ip, now);
+        // заодно чистим тех, кто давно отвалился
+        self.players_seen
+            .retain(|_, seen| now.duration_since(*seen) < PLAYER_TTL);
+    }
+
+    pub fn connected_players(&self) -> usize {
+        let now = Instant::now();
+        self.players_seen
+            .values()
+            .filter(|seen| now.duration_since(**seen) < PLAYER_TTL)
+            .count()
+    }
+
+    /// Текстовый формат экспозиции Prometheus.
+    pub fn render(&self, active_rooms: usize, situation_index: usize) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP clicker_clicks_total Accepted clicks per reaction.\n");
+        out.push_str("# TYPE clicker_clicks_total counter\n");
+        for (name, n) in ["lie", "delay", "freeze"].iter().zip(self.clicks) {
+            let _ = writeln!(out, "clicker_clicks_total{{reaction=\"{name}\"}} {n}");
+        }
+
+        out.push_str("# HELP clicker_clicks_rejected_total Clicks with an unknown reaction.\n");
+        out.push_str("# TYPE clicker_clicks_rejected_total counter\n");
+        let _ = writeln!(
+            out,
+            "clicker_clicks_rejected_total {}",
+            self.clicks_rejected
+        );
+
+        out.push_str("# HELP clicker_reveals_total Answers revealed by the admin.\n");
+        out.push_str("# TYPE clicker_reveals_total counter\n");
+        let _ = writeln!(out, "clicker_reveals_total {}", self.reveals);
+
+        out.push_str("# HELP clicker_active_rooms Rooms currently running a game.\n");
+        out.push_str("# TYPE clicker_active_rooms gauge\n");
+        let _ = writeln!(out, "clicker_active_rooms {active_rooms}");
+
+        out.push_str("# HELP clicker_connected_players Players that polled the server recently.\n");
+        out.push_str("# TYPE clicker_connected_players gauge\n");
+        let _ = writeln!(
+            out,
+            "clicker_connected_players {}",
+            self.connected_players()
+        );
+
+        out.push_str("# HELP clicker_situation_index Index of the current situation.\n");
+        out.push_str("# TYPE clicker_situation_index gauge\n");
+        let _ = writeln!(out, "clicker_situation_index {situation_index}");
+
+        out.push_str("# HELP clicker_request_duration_seconds Request latency per route.\n");
+        out.push_str("# TYPE clicker_request_duration_seconds histogram\n");
+        for (route, h) in &self.latency {
+            for (le, n) in LATENCY_BUCKETS.iter().zip(h.buckets) {
+                let _ = writeln!(
+                    out,
+                    "clicker_request_duration_seconds_bucket{{route=\"{route}\",le=\"{le}\"}} {n}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "clicker_request_duration_seconds_bucket{{route=\"{route}\",le=\"+Inf\"}} {}",
+                h.count
+            );
+            let _ = writeln!(
+                out,
+                "clicker_request_duration_seconds_sum{{route=\"{route}\"}} {}",
+                h.sum
+            );
+            let _ = writeln!(
+                out,
+                "clicker_request_duration_seconds_count{{route=\"{route}\"}} {}",
+                h.count
+            );
+        }
+
+        out
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    history::History,
+    meters::Meters,
+    players::Players,
+    questions::{QuestionBox, QuestionStatus},
+    ranking::Ballots,
+    AppState, Promotion, ShownResult,
+};
+
+// ===================== Сохранение состояния =====================
+
+// ситуации живут в коде, поэтому сохраняем только прогресс игры
+// и вставленные в колоду вопросы из зала
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    current_index: usize,
+    counts: [u64; 3],
+    last_result: Option<ShownResult>,
+    result_version: u64,
+    #[serde(default)]
+    questions: QuestionBox,
+    #[serde(default)]
+    players: Players,
+    #[serde(default)]
+    teams: Vec<String>,
+    #[serde(default)]
+    team_counts: Vec<[u64; 3]>,
+    #[serde(default)]
+    meters: Meters,
+    #[serde(default)]
+    ballots: Ballots,
+    #[serde(default)]
+    history: History,
+    #[serde(default)]
+    promoted: Vec<Promotion>,
+}
+
+/// Путь к файлу состояния: STATE_FILE или ./state.json.
+pub fn state_path() -> PathBuf {
+    std::env::var_os("STATE_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("state.json"))
+}
+
+pub fn save(st: &AppState, path: &Path) -> io::Result<()> {
+    let snap = Snapshot {
+        current_index: st.current_index,
+        counts: st.counts,
+        last_result: st.last_result.clone(),
+        result_version: st.result_version,
+        questions: st.questions.clone(),
+        players: st.players.clone(),
+        teams: st.teams.clone(),
+        team_counts: st.team_counts.clone(),
+        meters: st.meters.clone(),
+        ballots: st.ballots.clone(),
+        history: st.history.clone(),
+        promoted: st.promoted.clone(),
+    };
+    let json = serde_json::to_string_pretty(&snap).map_err(io::Error::other)?;
+    // пишем во временный файл и переименовываем, чтобы не оставить обрубок
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(tmp, path)
+}
+
+/// Подтягивает сохранённый прогресс, если файл есть. Отсутствие файла — не ошибка.
+pub fn load(st: &mut AppState, path: &Path) -> io::Result<bool> {
+    let json = match fs::read_to_string(path) {
+        Ok(j) => j,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let mut snap: Snapshot = serde_json::from_str(&json).map_err(io::Error::other)?;
+    // вопросы из зала вставляем в том же порядке — тогда индексы совпадут с сохранёнными
+    let mut situations = st.situations.clone();
+    let mut replayed = true;
+    for p in &snap.promoted {
+        if p.index > situations.len() {
+            replayed = false;
+            break;
+        }
+        situations.insert(p.index, p.situation());
+    }
+    // колода могла поменяться между запусками
+    if !replayed || snap.current_index >= situations.len() {
+        // прогресс не восстановить, но вопросы не теряем: их можно промоутить заново
+        for q in &mut snap.questions.items {
+            if q.status == QuestionStatus::Promoted {
+                q.status = QuestionStatus::Approved;
+            }
+        }
+        st.questions = snap.questions;
+        return Ok(false);
+    }
+    st.situations = situations;
+    st.promoted = snap.promoted;
+    st.current_index = snap.current_index;
+    st.counts = snap.counts;
+    st.last_result = snap.last_result;
+    st.result_version = snap.result_version;
+    st.questions = snap.questions;
+    st.players = snap.players;
+    // команды берутся из TEAMS; если состав другой, счёт не переносим, а игроков раскладываем заново
+    if snap.teams == st.teams && snap.team_counts.len() == st.team_counts.len() {
+        st.team_counts = snap.team_counts;
+    } else {
+        st.players.assign_teams(st.teams.len());
+    }
+    st.meters.restore(snap.meters);
+    st.ballots = snap.ballots;
+    st.history = snap.history;
+    Ok(true)
+}
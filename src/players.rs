@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ordered_tuple, random_u64, Reaction};
+
+// ===================== Игроки и прогнозы =====================
+
+// очки за угаданную пару и за угаданную реакцию-лидера
+pub const POINTS_PAIR: u64 = 2;
+pub const POINTS_WINNER: u64 = 1;
+pub const MAX_NAME_LEN: usize = 32;
+// регистрация открыта всем — без потолка одна вкладка раздует и память, и state.json
+pub const MAX_PLAYERS: usize = 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+    pub points: u64,
+    #[serde(default)]
+    pub team: Option<usize>,
+}
+
+/// Прогноз на текущую ситуацию: пара, которую выберет top_two, и/или лидер.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Prediction {
+    pub pair: Option<(Reaction, Reaction)>,
+    pub winner: Option<Reaction>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub points: u64,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Players {
+    // ключ — токен игрока, который клиент хранит у себя
+    by_id: HashMap<String, Player>,
+    #[serde(skip)]
+    predictions: HashMap<String, Prediction>,
+    // очки за текущую ситуацию уже начислены (повторный показ не удваивает)
+    #[serde(skip)]
+    scored: bool,
+    // сколько кому начислено за текущую ситуацию — чтобы откатить при сбросе
+    #[serde(skip)]
+    awarded: HashMap<String, u64>,
+}
+
+impl Players {
+    pub fn register(&mut self, raw_name: &str, team: Option<usize>) -> Result<String, String> {
+        let name = raw_name.trim();
+        if name.is_empty() {
+            return Err("Нужно имя".to_string());
+        }
+        if name.chars().count() > MAX_NAME_LEN {
+            return Err(format!("Имя длиннее {MAX_NAME_LEN} символов"));
+        }
+        if self.by_id.len() >= MAX_PLAYERS {
+            return Err("Комната заполнена, больше игроков не поместится".to_string());
+        }
+        let id = format!("{:016x}{:016x}", random_u64(), random_u64());
+        self.by_id.insert(
+            id.clone(),
+            Player {
+                name: name.to_string(),
+                points: 0,
+                team,
+            },
+        );
+        Ok(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Player> {
+        self.by_id.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    /// Команда с наименьшим числом игроков — туда и отправляем новичка.
+    pub fn smallest_team(&self, teams: usize) -> usize {
+        let mut sizes = vec![0usize; teams];
+        for t in self.by_id.values().filter_map(|p| p.team) {
+            if let Some(n) = sizes.get_mut(t) {
+                *n += 1;
+            }
+        }
+        (0..teams).min_by_key(|&t| sizes[t]).unwrap_or(0)
+    }
+
+    /// Состав команд поменялся: старые индексы больше ничего не значат,
+    /// поэтому раскладываем всех заново поровну (0 команд — без команд).
+    pub fn assign_teams(&mut self, teams: usize) {
+        let mut ids: Vec<_> = self.by_id.keys().cloned().collect();
+        ids.sort();
+        for (i, id) in ids.iter().enumerate() {
+            if let Some(p) = self.by_id.get_mut(id) {
+                p.team = (teams > 0).then(|| i % teams);
+            }
+        }
+    }
+
+    pub fn prediction(&self, id: &str) -> Option<Prediction> {
+        self.predictions.get(id).copied()
+    }
+
+    pub fn predict(&mut self, id: &str, mut p: Prediction) -> Result<(), String> {
+        if !self.by_id.contains_key(id) {
+            return Err("Неизвестный игрок, зайди заново".to_string());
+        }
+        if self.scored {
+            return Err("Ответ уже показан, прогнозы закрыты".to_string());
+        }
+        if let Some((a, b)) = p.pair {
+            if a == b {
+                return Err("В паре нужны две разные реакции".to_string());
+            }
+            p.pair = Some(ordered_tuple(a, b));
+        }
+        if p == Prediction::default() {
+            return Err("Пустой прогноз".to_string());
+        }
+        self.predictions.insert(id.to_string(), p);
+        Ok(())
+    }
+
+    /// Начисляет очки за текущую ситуацию. Возвращает, сколько игроков что-то угадали.
+    pub fn score(&mut self, pair: (Reaction, Reaction), winner: Reaction) -> usize {
+        if self.scored {
+            return 0;
+        }
+        self.scored = true;
+        let mut lucky = 0;
+        for (id, p) in &self.predictions {
+            let mut points = 0;
+            if p.pair == Some(pair) {
+                points += POINTS_PAIR;
+            }
+            if p.winner == Some(winner) {
+                points += POINTS_WINNER;
+            }
+            if points > 0 {
+                if let Some(player) = self.by_id.get_mut(id) {
+                    player.points += points;
+                    self.awarded.insert(id.clone(), points);
+                    lucky += 1;
+                }
+            }
+        }
+        lucky
+    }
+
+    /// Новая ситуация — новые прогнозы.
+    pub fn new_round(&mut self) {
+        self.predictions.clear();
+        self.scored = false;
+        self.awarded.clear();
+    }
+
+    /// Сброс голосования: очки за текущую ситуацию забираем, прогнозы открываем заново.
+    pub fn undo_round(&mut self) {
+        for (id, points) in self.awarded.drain() {
+            if let Some(player) = self.by_id.get_mut(&id) {
+                player.points = player.points.saturating_sub(points);
+            }
+        }
+        self.new_round();
+    }
+
+    pub fn leaderboard(&self, limit: usize) -> Vec<LeaderboardEntry> {
+        let mut v: Vec<_> = self
+            .by_id
+            .values()
+            .map(|p| LeaderboardEntry {
+                name: p.name.clone(),
+                points: p.points,
+            })
+            .collect();
+        v.sort_by(|a, b| b.points.cmp(&a.points).then_with(|| a.name.cmp(&b.name)));
+        v.truncate(limit);
+        v
+    }
+}
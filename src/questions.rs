@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+// ===================== Анонимные вопросы от игроков =====================
+
+pub const MAX_QUESTION_LEN: usize = 500;
+// защита от заспамленной очереди
+pub const MAX_PENDING: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionStatus {
+    Pending,
+    Approved,
+    Rejected,
+    // уже стал ситуацией в текущей сессии
+    Promoted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Question {
+    pub id: u64,
+    pub text: String,
+    pub status: QuestionStatus,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct QuestionBox {
+    pub items: Vec<Question>,
+    next_id: u64,
+}
+
+impl QuestionBox {
+    pub fn submit(&mut self, raw: &str) -> Result<u64, String> {
+        let text = clean(raw)?;
+        let pending = self
+            .items
+            .iter()
+            .filter(|q| q.status == QuestionStatus::Pending)
+            .count();
+        if pending >= MAX_PENDING {
+            return Err("Очередь вопросов переполнена, попробуй чуть позже".to_string());
+        }
+        self.next_id += 1;
+        self.items.push(Question {
+            id: self.next_id,
+            text,
+            status: QuestionStatus::Pending,
+        });
+        Ok(self.next_id)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut Question> {
+        self.items.iter_mut().find(|q| q.id == id)
+    }
+}
+
+/// Обрезает пробелы и проверяет длину — и для игроков, и для правок модератора.
+pub fn clean(raw: &str) -> Result<String, String> {
+    let text = raw.trim();
+    if text.is_empty() {
+        return Err("Пустой вопрос".to_string());
+    }
+    if text.chars().count() > MAX_QUESTION_LEN {
+        return Err(format!("Вопрос длиннее {MAX_QUESTION_LEN} символов"));
+    }
+    Ok(text.to_string())
+}
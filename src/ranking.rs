@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{idx_to_reaction, Reaction};
+
+// ===================== Ранжированное голосование =====================
+//
+// Вместо кликов игрок расставляет реакции по местам (все три или только две
+// верхние). Пару выбираем по очкам метода, а не по сырым кликам.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VotingMode {
+    // обычные клики, пара — top_two по количеству
+    #[default]
+    Clicks,
+    // Борда: 1-е место — 2 очка, 2-е — 1, 3-е и неназванная — 0
+    Borda,
+    // попарно (Коупленд): победа над соперником — 2 очка, ничья — 1
+    Pairwise,
+}
+
+impl VotingMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "clicks" => Some(VotingMode::Clicks),
+            "borda" => Some(VotingMode::Borda),
+            "pairwise" => Some(VotingMode::Pairwise),
+            _ => None,
+        }
+    }
+
+    pub fn is_ranked(self) -> bool {
+        self != VotingMode::Clicks
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Ballots {
+    list: Vec<Vec<Reaction>>,
+    // токен игрока -> его бюллетень: повторный голос заменяет прошлый
+    by_player: HashMap<String, usize>,
+}
+
+/// Полная раскладка голосования для показа.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tally {
+    pub method: VotingMode,
+    pub ballots: usize,
+    // по индексу реакции, как counts
+    pub scores: [u64; 3],
+    // positions[r][p] — сколько бюллетеней поставили реакцию r на место p
+    pub positions: [[u64; 3]; 3],
+    // pairwise[a][b] — сколько бюллетеней ставят a выше b
+    pub pairwise: [[u64; 3]; 3],
+    // итоговый порядок; при равенстве очков — порядок реакций, как в top_two
+    pub order: [Reaction; 3],
+}
+
+impl Ballots {
+    pub fn cast(&mut self, ranking: &[Reaction], player: Option<&str>) -> Result<(), String> {
+        if !(2..=3).contains(&ranking.len()) {
+            return Err("Расставь по местам две или три реакции".to_string());
+        }
+        for (i, r) in ranking.iter().enumerate() {
+            if ranking[..i].contains(r) {
+                return Err("Реакции в рейтинге не должны повторяться".to_string());
+            }
+        }
+        let ballot = ranking.to_vec();
+        match player.and_then(|id| self.by_player.get(id)) {
+            Some(&i) => self.list[i] = ballot,
+            None => {
+                if let Some(id) = player {
+                    self.by_player.insert(id.to_string(), self.list.len());
+                }
+                self.list.push(ballot);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.by_player.clear();
+    }
+
+    pub fn tally(&self, method: VotingMode) -> Tally {
+        let mut positions = [[0u64; 3]; 3];
+        let mut pairwise = [[0u64; 3]; 3];
+        for ballot in &self.list {
+            for (place, &r) in ballot.iter().enumerate() {
+                positions[r as usize][place] += 1;
+                // выше всех, кто ниже в бюллетене, и всех неназванных
+                for (other, n) in pairwise[r as usize].iter_mut().enumerate() {
+                    let below = ballot[place + 1..].iter().any(|&b| b as usize == other)
+                        || !ballot.iter().any(|&b| b as usize == other);
+                    if below {
+                        *n += 1;
+                    }
+                }
+            }
+        }
+
+        let mut scores = [0u64; 3];
+        for (r, score) in scores.iter_mut().enumerate() {
+            *score = match method {
+                VotingMode::Borda => positions[r][0] * 2 + positions[r][1],
+                VotingMode::Pairwise => (0..3)
+                    .filter(|&o| o != r)
+                    .map(|o| match pairwise[r][o].cmp(&pairwise[o][r]) {
+                        std::cmp::Ordering::Greater => 2,
+                        std::cmp::Ordering::Equal => 1,
+                        std::cmp::Ordering::Less => 0,
+                    })
+                    .sum(),
+                // в режиме кликов бюллетеней нет
+                VotingMode::Clicks => 0,
+            };
+        }
+
+        let mut idx = [0usize, 1, 2];
+        idx.sort_by_key(|&r| std::cmp::Reverse(scores[r]));
+        Tally {
+            method,
+            ballots: self.list.len(),
+            scores,
+            positions,
+            pairwise,
+            order: idx.map(idx_to_reaction),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+// ===================== Постепенный показ ответа =====================
+//
+// После «Показать ответ» клиенты видят сначала полоски голосов, потом отсчёт
+// и только затем текст ответа. Этапы считает сервер по своим часам, так что все
+// экраны переключаются вместе; ведущий может перескочить на следующий этап.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Bars,
+    Countdown,
+    Answer,
+}
+
+/// Длительность этапов до ответа. Нулевой этап пропускается.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Config {
+    pub bars_ms: u64,
+    pub countdown_ms: u64,
+}
+
+impl Config {
+    /// REVEAL_STAGES="bars=2,countdown=3" — секунды; "off" — ответ сразу.
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        let mut config = Config::default();
+        if s.trim() == "off" {
+            return Ok(config);
+        }
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, secs) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected stage=seconds, got '{part}'"))?;
+            let ms = secs
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && (0.0..=60.0).contains(v))
+                .map(|v| (v * 1000.0) as u64)
+                .ok_or_else(|| format!("'{secs}' is not a number of seconds from 0 to 60"))?;
+            match name.trim() {
+                "bars" => config.bars_ms = ms,
+                "countdown" => config.countdown_ms = ms,
+                other => {
+                    return Err(format!(
+                        "unknown stage '{other}', expected bars or countdown"
+                    ))
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    fn stages(&self) -> [(Stage, u64); 2] {
+        [
+            (Stage::Bars, self.bars_ms),
+            (Stage::Countdown, self.countdown_ms),
+        ]
+    }
+}
+
+/// Что сейчас показывают клиенты и сколько до следующего этапа.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Status {
+    pub stage: Stage,
+    pub left_ms: u64,
+}
+
+impl Status {
+    pub const DONE: Status = Status {
+        stage: Stage::Answer,
+        left_ms: 0,
+    };
+}
+
+/// Идущий показ. Не сохраняется: после перезапуска ответ виден сразу.
+#[derive(Clone, Debug)]
+pub struct Reveal {
+    started: Instant,
+    // на сколько ведущий промотал вперёд
+    skipped: Duration,
+}
+
+impl Reveal {
+    pub fn start(now: Instant) -> Self {
+        Reveal {
+            started: now,
+            skipped: Duration::ZERO,
+        }
+    }
+
+    pub fn status(&self, config: &Config, now: Instant) -> Status {
+        let mut elapsed =
+            (now.saturating_duration_since(self.started) + self.skipped).as_millis() as u64;
+        for (stage, ms) in config.stages() {
+            if elapsed < ms {
+                return Status {
+                    stage,
+                    left_ms: ms - elapsed,
+                };
+            }
+            elapsed -= ms;
+        }
+        Status::DONE
+    }
+
+    /// Когда ответ откроется всем (или когда открылся).
+    pub fn answer_at(&self, config: &Config) -> Instant {
+        let stages = Duration::from_millis(config.bars_ms + config.countdown_ms);
+        (self.started + stages)
+            .checked_sub(self.skipped)
+            .unwrap_or(self.started)
+            .max(self.started)
+    }
+
+    /// Перескок в начало следующего этапа.
+    pub fn skip(&mut self, config: &Config, now: Instant) -> Status {
+        let left = self.status(config, now).left_ms;
+        self.skipped += Duration::from_millis(left);
+        self.status(config, now)
+    }
+}
@@ -0,0 +1,415 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::template::Vars;
+
+// ===================== Условия для ответов =====================
+//
+// Ситуация может перечислить правила «условие -> ответ». Они проверяются по
+// порядку до выбора ответа по паре; сработало первое — его ответ и показываем.
+//
+//   freeze > 70%                  доля заморозки больше 70%
+//   total < 5                     проголосовали меньше пяти раз
+//   lie within 10% of delay       доли врать и отложить отличаются не больше чем на 10%
+//   lie within 3 of delay         то же в голосах
+//   not (total == 0) and (freeze >= lie or player_count > 20)
+//
+// Переменные: lie, delay, freeze, total, player_count. Проценты сравниваются
+// только с реакциями (это их доля от total), числа — с чем угодно.
+
+/// Правило колоды: условие и ответ (в ответе работают шаблоны).
+/// Условие разбирается один раз, при создании правила.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub when: String,
+    pub answer: String,
+    cond: Result<Condition, RuleError>,
+}
+
+impl Rule {
+    pub fn new(when: &str, answer: &str) -> Self {
+        Rule {
+            when: when.to_string(),
+            answer: answer.to_string(),
+            cond: compile(when),
+        }
+    }
+
+    /// Ошибка разбора условия — для проверки колоды при загрузке.
+    pub fn error(&self) -> Option<&RuleError> {
+        self.cond.as_ref().err()
+    }
+
+    /// Сработало ли правило. Колода с битым условием не проходит validate_deck.
+    pub fn matches(&self, vars: &Vars) -> bool {
+        self.cond.as_ref().is_ok_and(|c| c.eval(vars))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleError {
+    pub pos: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at byte {})", self.msg, self.pos)
+    }
+}
+
+fn err<T>(pos: usize, msg: impl Into<String>) -> Result<T, RuleError> {
+    Err(RuleError {
+        pos,
+        msg: msg.into(),
+    })
+}
+
+// ---------- лексер ----------
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Tok {
+    Ident(String),
+    Num(u64),
+    Percent,
+    Open,
+    Close,
+    Op(Op),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+fn lex(src: &str) -> Result<Vec<(usize, Tok)>, RuleError> {
+    let bytes = src.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let two = |s: &str| src[i..].starts_with(s);
+        let tok = match c {
+            b' ' | b'\t' | b'\n' => {
+                i += 1;
+                continue;
+            }
+            b'(' => Tok::Open,
+            b')' => Tok::Close,
+            b'%' => Tok::Percent,
+            _ if two("<=") => Tok::Op(Op::Le),
+            _ if two(">=") => Tok::Op(Op::Ge),
+            _ if two("==") => Tok::Op(Op::Eq),
+            _ if two("!=") => Tok::Op(Op::Ne),
+            b'<' => Tok::Op(Op::Lt),
+            b'>' => Tok::Op(Op::Gt),
+            b'0'..=b'9' => {
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let n = src[start..i]
+                    .parse()
+                    .or_else(|_| err(start, "number is too large"))?;
+                out.push((start, Tok::Num(n)));
+                continue;
+            }
+            b'a'..=b'z' | b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_lowercase() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                out.push((start, Tok::Ident(src[start..i].to_string())));
+                continue;
+            }
+            _ => {
+                let ch = src[i..].chars().next().unwrap_or('?');
+                return err(i, format!("unexpected character '{ch}'"));
+            }
+        };
+        i += match tok {
+            Tok::Op(Op::Le | Op::Ge | Op::Eq | Op::Ne) => 2,
+            _ => 1,
+        };
+        out.push((start, tok));
+    }
+    Ok(out)
+}
+
+// ---------- разбор и проверка типов ----------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Var {
+    Reaction(usize),
+    Total,
+    Players,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+    Var(Var),
+    Num(u64),
+    Pct(u64),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Value, Op, Value),
+    // |a - b| <= tolerance; в процентах — по долям
+    Within(Value, Value, Value),
+}
+
+struct Parser {
+    toks: Vec<(usize, Tok)>,
+    at: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn pos(&self) -> usize {
+        self.toks.get(self.at).map_or(self.end, |(p, _)| *p)
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.at).map(|(_, t)| t)
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Ident(w)) if w == word) {
+            self.at += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, RuleError> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, RuleError> {
+        let mut left = self.unary()?;
+        while self.keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, RuleError> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Tok::Open) {
+            self.at += 1;
+            let inner = self.or()?;
+            if self.peek() != Some(&Tok::Close) {
+                return err(self.pos(), "expected ')'");
+            }
+            self.at += 1;
+            return Ok(inner);
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Expr, RuleError> {
+        let left_pos = self.pos();
+        let left = self.value()?;
+        if self.keyword("within") {
+            let tol_pos = self.pos();
+            let tol = self.value()?;
+            if !self.keyword("of") {
+                return err(self.pos(), "expected 'of' after the tolerance");
+            }
+            let right_pos = self.pos();
+            let right = self.value()?;
+            match tol {
+                Value::Pct(_) => {
+                    for (v, p) in [(left, left_pos), (right, right_pos)] {
+                        if !matches!(v, Value::Var(Var::Reaction(_))) {
+                            return err(p, "percent tolerance compares reaction shares, expected lie, delay or freeze");
+                        }
+                    }
+                }
+                Value::Num(_) => {
+                    for (v, p) in [(left, left_pos), (right, right_pos)] {
+                        if !matches!(v, Value::Var(_)) {
+                            return err(p, "expected a variable");
+                        }
+                    }
+                }
+                Value::Var(_) => return err(tol_pos, "tolerance must be a number or a percent"),
+            }
+            return Ok(Expr::Within(left, tol, right));
+        }
+        let op = match self.peek() {
+            Some(Tok::Op(op)) => *op,
+            _ => {
+                return err(
+                    self.pos(),
+                    "expected a comparison (<, <=, >, >=, ==, !=) or 'within'",
+                )
+            }
+        };
+        self.at += 1;
+        let right_pos = self.pos();
+        let right = self.value()?;
+        check_cmp(left, left_pos, right, right_pos)?;
+        Ok(Expr::Cmp(left, op, right))
+    }
+
+    fn value(&mut self) -> Result<Value, RuleError> {
+        let pos = self.pos();
+        let Some((_, tok)) = self.toks.get(self.at).cloned() else {
+            return err(pos, "unexpected end of condition");
+        };
+        self.at += 1;
+        match tok {
+            Tok::Num(n) => {
+                if self.peek() == Some(&Tok::Percent) {
+                    self.at += 1;
+                    if n > 100 {
+                        return err(pos, "percent above 100");
+                    }
+                    Ok(Value::Pct(n))
+                } else {
+                    Ok(Value::Num(n))
+                }
+            }
+            Tok::Ident(name) => match name.as_str() {
+                "lie" => Ok(Value::Var(Var::Reaction(0))),
+                "delay" => Ok(Value::Var(Var::Reaction(1))),
+                "freeze" => Ok(Value::Var(Var::Reaction(2))),
+                "total" => Ok(Value::Var(Var::Total)),
+                "player_count" => Ok(Value::Var(Var::Players)),
+                _ => err(
+                    pos,
+                    format!(
+                        "unknown variable '{name}', expected one of: lie, delay, freeze, total, player_count"
+                    ),
+                ),
+            },
+            _ => err(pos, "expected a variable, a number or a percent"),
+        }
+    }
+}
+
+fn check_cmp(left: Value, lp: usize, right: Value, rp: usize) -> Result<(), RuleError> {
+    let reaction = |v: Value| matches!(v, Value::Var(Var::Reaction(_)));
+    match (left, right) {
+        (Value::Var(_), Value::Var(_) | Value::Num(_)) | (Value::Num(_), Value::Var(_)) => Ok(()),
+        (l, Value::Pct(_)) if reaction(l) => Ok(()),
+        (Value::Pct(_), r) if reaction(r) => Ok(()),
+        (Value::Var(_), Value::Pct(_)) => err(lp, NO_SHARE),
+        (Value::Pct(_), Value::Var(_)) => err(rp, NO_SHARE),
+        _ => err(lp, "condition compares two constants"),
+    }
+}
+
+const NO_SHARE: &str = "only lie, delay and freeze have a share, compare this one with a number";
+
+fn parse(src: &str) -> Result<Expr, RuleError> {
+    let mut p = Parser {
+        toks: lex(src)?,
+        at: 0,
+        end: src.len(),
+    };
+    if p.toks.is_empty() {
+        return err(0, "empty condition");
+    }
+    let expr = p.or()?;
+    if p.at < p.toks.len() {
+        return err(p.pos(), "unexpected token after the condition");
+    }
+    Ok(expr)
+}
+
+/// Разобранное и проверенное по типам условие.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition(Expr);
+
+/// Разбор условия: синтаксис и типы.
+pub fn compile(src: &str) -> Result<Condition, RuleError> {
+    parse(src).map(Condition)
+}
+
+// ---------- вычисление ----------
+
+fn total(vars: &Vars) -> u64 {
+    vars.counts.iter().sum()
+}
+
+fn count(vars: &Vars, v: Var) -> u64 {
+    match v {
+        Var::Reaction(i) => vars.counts[i],
+        Var::Total => total(vars),
+        Var::Players => vars.player_count as u64,
+    }
+}
+
+// доли сравниваем в целых: count / total против pct / 100
+fn scaled(vars: &Vars, v: Value) -> u64 {
+    match v {
+        Value::Var(var) => count(vars, var) * 100,
+        Value::Pct(p) => p * total(vars),
+        Value::Num(n) => n,
+    }
+}
+
+fn plain(vars: &Vars, v: Value) -> u64 {
+    match v {
+        Value::Var(var) => count(vars, var),
+        Value::Num(n) | Value::Pct(n) => n,
+    }
+}
+
+fn eval_expr(e: &Expr, vars: &Vars) -> bool {
+    match e {
+        Expr::Or(a, b) => eval_expr(a, vars) || eval_expr(b, vars),
+        Expr::And(a, b) => eval_expr(a, vars) && eval_expr(b, vars),
+        Expr::Not(a) => !eval_expr(a, vars),
+        Expr::Cmp(l, op, r) => {
+            let share = matches!(l, Value::Pct(_)) || matches!(r, Value::Pct(_));
+            let ord = if share {
+                // без голосов доли не определены — такие условия не срабатывают
+                if total(vars) == 0 {
+                    return false;
+                }
+                scaled(vars, *l).cmp(&scaled(vars, *r))
+            } else {
+                plain(vars, *l).cmp(&plain(vars, *r))
+            };
+            match op {
+                Op::Lt => ord == Ordering::Less,
+                Op::Le => ord != Ordering::Greater,
+                Op::Gt => ord == Ordering::Greater,
+                Op::Ge => ord != Ordering::Less,
+                Op::Eq => ord == Ordering::Equal,
+                Op::Ne => ord != Ordering::Equal,
+            }
+        }
+        Expr::Within(l, tol, r) => match tol {
+            Value::Pct(p) => {
+                let t = total(vars);
+                t > 0 && plain(vars, *l).abs_diff(plain(vars, *r)) * 100 <= p * t
+            }
+            _ => plain(vars, *l).abs_diff(plain(vars, *r)) <= plain(vars, *tol),
+        },
+    }
+}
+
+impl Condition {
+    /// Выполнилось ли условие при таком раскладе голосов.
+    pub fn eval(&self, vars: &Vars) -> bool {
+        eval_expr(&self.0, vars)
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+use crate::{top_two, Reaction};
+
+// ===================== Шаблоны в ответах =====================
+//
+// В тексте ответа или описания можно писать {total}, {lie_pct}, {winner} и т.д.
+// Фигурные скобки как текст — {{ и }}.
+
+const VARIABLES: &[&str] = &[
+    "total",
+    "lie",
+    "delay",
+    "freeze",
+    "lie_pct",
+    "delay_pct",
+    "freeze_pct",
+    "winner",
+    "runner_up",
+    "player_count",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    Unclosed { pos: usize },
+    StrayClose { pos: usize },
+    // expected — понятные этому шаблону имена, через запятую
+    Unknown { name: String, expected: String },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed { pos } => write!(f, "unclosed '{{' at byte {pos}"),
+            TemplateError::StrayClose { pos } => {
+                write!(
+                    f,
+                    "unmatched '}}' at byte {pos} (use '}}}}' for a literal brace)"
+                )
+            }
+            TemplateError::Unknown { name, expected } => write!(
+                f,
+                "unknown placeholder {{{name}}}, expected one of: {expected}"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Part<'a> {
+    Text(&'a str),
+    Var(&'a str),
+}
+
+fn parse<'a>(
+    src: &'a str,
+    known: &dyn Fn(&str) -> bool,
+    expected: &str,
+) -> Result<Vec<Part<'a>>, TemplateError> {
+    let mut parts = Vec::new();
+    let mut rest = src;
+    let mut offset = 0;
+    while let Some(i) = rest.find(['{', '}']) {
+        if i > 0 {
+            parts.push(Part::Text(&rest[..i]));
+        }
+        let tail = &rest[i..];
+        let consumed = if tail.starts_with("{{") {
+            parts.push(Part::Text("{"));
+            2
+        } else if tail.starts_with("}}") {
+            parts.push(Part::Text("}"));
+            2
+        } else if tail.starts_with('}') {
+            return Err(TemplateError::StrayClose { pos: offset + i });
+        } else {
+            let end = tail
+                .find('}')
+                .ok_or(TemplateError::Unclosed { pos: offset + i })?;
+            let name = tail[1..end].trim();
+            if !known(name) {
+                return Err(TemplateError::Unknown {
+                    name: name.to_string(),
+                    expected: expected.to_string(),
+                });
+            }
+            parts.push(Part::Var(name));
+            end + 1
+        };
+        offset += i + consumed;
+        rest = &rest[i + consumed..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    Ok(parts)
+}
+
+fn is_variable(name: &str) -> bool {
+    VARIABLES.contains(&name)
+}
+
+/// Проверка при загрузке колоды.
+pub fn validate(src: &str) -> Result<(), TemplateError> {
+    parse(src, &is_variable, &VARIABLES.join(", ")).map(|_| ())
+}
+
+/// Проверка шаблона со своим набором подстановок (например, запроса в трекер):
+/// возвращает имена, которые в нём встречаются. `expected` уходит в ошибку Unknown.
+pub fn placeholders<'a>(
+    src: &'a str,
+    known: &dyn Fn(&str) -> bool,
+    expected: &str,
+) -> Result<Vec<&'a str>, TemplateError> {
+    Ok(parse(src, known, expected)?
+        .into_iter()
+        .filter_map(|p| match p {
+            Part::Var(name) => Some(name),
+            Part::Text(_) => None,
+        })
+        .collect())
+}
+
+/// Живые данные голосования для подстановки.
+pub struct Vars {
+    pub counts: [u64; 3],
+    pub player_count: usize,
+}
+
+fn reaction_name(r: Reaction) -> &'static str {
+    match r {
+        Reaction::Lie => "врать",
+        Reaction::Delay => "отложить",
+        Reaction::Freeze => "заморозить",
+    }
+}
+
+impl Vars {
+    fn pct(&self, idx: usize) -> u64 {
+        let total: u64 = self.counts.iter().sum();
+        (self.counts[idx] * 100 + total / 2)
+            .checked_div(total)
+            .unwrap_or(0)
+    }
+
+    fn get(&self, name: &str) -> String {
+        let (winner, runner_up) = top_two(&self.counts);
+        match name {
+            "total" => self.counts.iter().sum::<u64>().to_string(),
+            "lie" => self.counts[0].to_string(),
+            "delay" => self.counts[1].to_string(),
+            "freeze" => self.counts[2].to_string(),
+            "lie_pct" => self.pct(0).to_string(),
+            "delay_pct" => self.pct(1).to_string(),
+            "freeze_pct" => self.pct(2).to_string(),
+            "winner" => reaction_name(winner).to_string(),
+            "runner_up" => reaction_name(runner_up).to_string(),
+            "player_count" => self.player_count.to_string(),
+            // parse() пропускает только известные имена
+            _ => String::new(),
+        }
+    }
+}
+
+/// Подставляет значения. Колода проверена при загрузке, но если шаблон всё же
+/// битый — отдаём текст как есть, а не ломаем показ ответа.
+pub fn render(src: &str, vars: &Vars) -> String {
+    render_with(src, &is_variable, &|name| vars.get(name))
+}
+
+/// Подстановка со своим набором имён; битый шаблон тоже отдаётся как есть.
+pub fn render_with(
+    src: &str,
+    known: &dyn Fn(&str) -> bool,
+    get: &dyn Fn(&str) -> String,
+) -> String {
+    let Ok(parts) = parse(src, known, "") else {
+        return src.to_string();
+    };
+    parts
+        .into_iter()
+        .map(|p| match p {
+            Part::Text(t) => t.to_string(),
+            Part::Var(name) => get(name),
+        })
+        .collect()
+}
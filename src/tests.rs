@@ -0,0 +1,2067 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use super::*;
+
+// ===================== Хелперы =====================
+
+fn test_situations() -> Vec<Situation> {
+    vec![
+        Situation {
+            title: "Первая".to_string(),
+            description: "Первая ситуация".to_string(),
+            answers: make_answers("врать+отложить", "врать+заморозить", "отложить+заморозить"),
+            ..Default::default()
+        },
+        Situation {
+            title: "Вторая".to_string(),
+            description: "Вторая ситуация".to_string(),
+            answers: make_answers("ЛД-2", "ЛЗ-2", "ОЗ-2"),
+            ..Default::default()
+        },
+    ]
+}
+
+fn test_app() -> (Router, Shared) {
+    let state = Arc::new(Mutex::new(AppState::new(test_situations())));
+    (build_app(state.clone()), state)
+}
+
+async fn send(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap()
+    };
+    (status, body)
+}
+
+async fn get_json(app: &Router, uri: &str) -> Value {
+    let (status, body) = send(app, Request::get(uri).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK, "GET {uri}: {body}");
+    body
+}
+
+async fn post_json(app: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    let req = Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    send(app, req).await
+}
+
+async fn post_empty(app: &Router, uri: &str) -> Value {
+    let (status, body) = send(app, Request::post(uri).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK, "POST {uri}: {body}");
+    body
+}
+
+// игрокам ответ приходит с этапом показа; без этапов он сразу целиком
+fn without_reveal(mut result: Value) -> Value {
+    let reveal = result.as_object_mut().unwrap().remove("reveal");
+    assert_eq!(reveal, Some(json!({ "stage": "answer", "left_ms": 0 })));
+    result
+}
+
+async fn click(app: &Router, reaction: &str, times: usize) {
+    for _ in 0..times {
+        let (status, body) = post_json(app, "/api/click", json!({ "reaction": reaction })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "ok": true }));
+    }
+}
+
+// ===================== top_two =====================
+
+#[test]
+fn top_two_picks_two_largest() {
+    assert_eq!(top_two(&[1, 5, 3]), (Reaction::Delay, Reaction::Freeze));
+    assert_eq!(top_two(&[7, 0, 3]), (Reaction::Lie, Reaction::Freeze));
+}
+
+#[test]
+fn top_two_ties_keep_reaction_order() {
+    // при равенстве выигрывает реакция, что левее: врать < отложить < заморозить
+    assert_eq!(top_two(&[0, 0, 0]), (Reaction::Lie, Reaction::Delay));
+    assert_eq!(top_two(&[2, 2, 2]), (Reaction::Lie, Reaction::Delay));
+    assert_eq!(top_two(&[3, 1, 1]), (Reaction::Lie, Reaction::Delay));
+    assert_eq!(top_two(&[1, 4, 4]), (Reaction::Delay, Reaction::Freeze));
+}
+
+// ===================== HTTP =====================
+
+#[tokio::test]
+async fn click_show_next_reset_flow() {
+    let (app, _) = test_app();
+
+    let cur = get_json(&app, "/api/current").await;
+    assert_eq!(cur["title"], "Первая");
+    assert_eq!(get_json(&app, "/api/result").await, Value::Null);
+
+    click(&app, "lie", 2).await;
+    click(&app, "freeze", 3).await;
+    click(&app, "delay", 1).await;
+
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["situation_title"], "Первая");
+    assert_eq!(shown["answer"], "врать+заморозить");
+    assert_eq!(shown["counts"], json!([2, 1, 3]));
+    assert_eq!(without_reveal(get_json(&app, "/api/result").await), shown);
+
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/current").await["title"], "Вторая");
+    assert_eq!(get_json(&app, "/api/result").await, Value::Null);
+
+    click(&app, "delay", 1).await;
+    click(&app, "freeze", 1).await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], "ОЗ-2");
+    assert_eq!(shown["counts"], json!([0, 1, 1]));
+
+    post_empty(&app, "/admin/reset").await;
+    assert_eq!(get_json(&app, "/api/result").await, Value::Null);
+    assert_eq!(
+        get_json(&app, "/admin/show").await["counts"],
+        json!([0, 0, 0])
+    );
+
+    // после последней ситуации колода идёт по кругу
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/current").await["title"], "Первая");
+}
+
+#[tokio::test]
+async fn show_with_tied_counts() {
+    let (app, _) = test_app();
+    click(&app, "delay", 2).await;
+    click(&app, "freeze", 2).await;
+    click(&app, "lie", 2).await;
+    assert_eq!(
+        get_json(&app, "/admin/show").await["answer"],
+        "врать+отложить"
+    );
+}
+
+#[tokio::test]
+async fn unknown_reaction_is_ignored() {
+    let (app, state) = test_app();
+    click(&app, "panic", 3).await;
+    click(&app, "Lie", 1).await;
+    assert_eq!(lock(&state).counts, [0, 0, 0]);
+
+    let metrics = app
+        .clone()
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let text = to_bytes(metrics.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(text.to_vec()).unwrap();
+    assert!(text.contains("clicker_clicks_rejected_total 4"));
+}
+
+#[tokio::test]
+async fn malformed_click_returns_error_json() {
+    let (app, _) = test_app();
+    let (status, body) = post_json(&app, "/api/click", json!({ "nope": 1 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["ok"], false);
+}
+
+#[tokio::test]
+async fn result_version_increments_on_every_show() {
+    let (app, _) = test_app();
+    assert_eq!(get_json(&app, "/admin/show").await["version"], 1);
+    assert_eq!(get_json(&app, "/admin/show").await["version"], 2);
+
+    // версия сквозная: ни next, ни reset её не сбрасывают
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/admin/show").await["version"], 3);
+    post_empty(&app, "/admin/reset").await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["version"], 4);
+    assert_eq!(get_json(&app, "/api/result").await["version"], 4);
+}
+
+#[tokio::test]
+async fn poisoned_state_does_not_brick_the_game() {
+    let (app, state) = test_app();
+    let poisoner = state.clone();
+    let _ = std::thread::spawn(move || {
+        let _guard = poisoner.lock().unwrap();
+        panic!("boom");
+    })
+    .join();
+    assert!(state.is_poisoned());
+
+    click(&app, "lie", 1).await;
+    assert_eq!(get_json(&app, "/api/current").await["title"], "Первая");
+    assert!(!state.is_poisoned());
+}
+
+#[tokio::test]
+async fn static_pages_survive_poisoned_state() {
+    let (app, state) = test_app();
+    let poisoner = state.clone();
+    let _ = std::thread::spawn(move || {
+        let _guard = poisoner.lock().unwrap();
+        panic!("boom");
+    })
+    .join();
+    assert!(state.is_poisoned());
+
+    let resp = app
+        .clone()
+        .oneshot(Request::get("/present").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn present_state_follows_admin_actions() {
+    let (app, _) = test_app();
+    let p = get_json(&app, "/api/present").await;
+    assert_eq!(p["title"], "Первая");
+    assert_eq!(p["clicks"], 0);
+    assert_eq!(p["result"], Value::Null);
+
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    let shown = get_json(&app, "/admin/show").await;
+    let p = get_json(&app, "/api/present").await;
+    assert_eq!(p["clicks"], 3);
+    assert_eq!(without_reveal(p["result"].clone()), shown);
+
+    post_empty(&app, "/admin/next").await;
+    let p = get_json(&app, "/api/present").await;
+    assert_eq!(p["title"], "Вторая");
+    assert_eq!(p["result"], Value::Null);
+}
+
+#[tokio::test]
+async fn present_shows_server_notice() {
+    let (app, state) = test_app();
+    assert_eq!(get_json(&app, "/api/present").await["notice"], Value::Null);
+
+    lock(&state).notice = Some("Сервер перезапускается".to_string());
+    assert_eq!(
+        get_json(&app, "/api/present").await["notice"],
+        "Сервер перезапускается"
+    );
+}
+
+#[tokio::test]
+async fn join_info_and_short_link_use_room_code() {
+    let (app, state) = test_app();
+    lock(&state).room_code = Some("KX7P".to_string());
+
+    let req = Request::get("/api/join")
+        .header("host", "10.0.0.5:3000")
+        .body(Body::empty())
+        .unwrap();
+    let (_, info) = send(&app, req).await;
+    assert_eq!(info["url"], "http://10.0.0.5:3000/r/KX7P");
+    assert_eq!(info["code"], "KX7P");
+
+    let ok = app
+        .clone()
+        .oneshot(Request::get("/r/kx7p").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(ok.headers()["location"], "/");
+    let bad = app
+        .clone()
+        .oneshot(Request::get("/r/AAAA").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(bad.headers()["location"], "/join?bad=1");
+}
+
+#[tokio::test]
+async fn qr_code_is_served_as_svg_and_png() {
+    let (app, state) = test_app();
+    lock(&state).public_url = Some("https://clicker.example/".to_string());
+
+    let svg = app
+        .clone()
+        .oneshot(Request::get("/qr.svg").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(svg.headers()["content-type"], "image/svg+xml");
+    let body = to_bytes(svg.into_body(), usize::MAX).await.unwrap();
+    assert!(body.starts_with(b"<?xml"));
+
+    let png = app
+        .clone()
+        .oneshot(Request::get("/qr.png").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(png.headers()["content-type"], "image/png");
+    let body = to_bytes(png.into_body(), usize::MAX).await.unwrap();
+    assert!(body.starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn room_codes_are_short_and_readable() {
+    let code = join::generate_room_code();
+    assert_eq!(code.len(), 4);
+    assert!(code.chars().all(|c| !"01OIL".contains(c)));
+    assert_eq!(join::normalize_code(" kx 7p "), "KX7P");
+}
+
+// ===================== Варианты ответов =====================
+
+const PAIR: (Reaction, Reaction) = (Reaction::Lie, Reaction::Delay);
+
+#[test]
+fn round_robin_cycles_through_variants() {
+    let mut p = VariantPicker::new(VariantStrategy::RoundRobin);
+    let picks: Vec<_> = (0..5).map(|_| p.pick(0, PAIR, 3)).collect();
+    assert_eq!(picks, [0, 1, 2, 0, 1]);
+    // у другой ситуации свой счётчик
+    assert_eq!(p.pick(1, PAIR, 3), 0);
+}
+
+#[test]
+fn least_recently_shown_prefers_stale_variants() {
+    let mut p = VariantPicker::new(VariantStrategy::LeastRecentlyShown);
+    assert_eq!(p.pick(0, PAIR, 2), 0);
+    assert_eq!(p.pick(0, PAIR, 2), 1);
+    assert_eq!(p.pick(0, PAIR, 2), 0);
+    // пул вырос — новый вариант ещё не показывали
+    assert_eq!(p.pick(0, PAIR, 3), 2);
+}
+
+#[test]
+fn random_variant_stays_in_range() {
+    let mut p = VariantPicker::new(VariantStrategy::Random);
+    assert!((0..50).all(|_| p.pick(0, PAIR, 4) < 4));
+    assert_eq!(p.pick(0, PAIR, 1), 0);
+}
+
+#[tokio::test]
+async fn show_records_chosen_variant() {
+    let (app, state) = test_app();
+    lock(&state).situations[0]
+        .answers
+        .insert(PAIR, vec!["раз".to_string(), "два".to_string()]);
+
+    let first = get_json(&app, "/admin/show").await;
+    assert_eq!(first["answer"], "раз");
+    assert_eq!(first["variant"], 0);
+    assert_eq!(first["variants"], 2);
+
+    let second = get_json(&app, "/admin/show").await;
+    assert_eq!(second["answer"], "два");
+    assert_eq!(second["variant"], 1);
+}
+
+// ===================== Шаблоны =====================
+
+#[test]
+fn template_renders_live_vote_data() {
+    let vars = template::Vars {
+        counts: [6, 3, 1],
+        player_count: 12,
+    };
+    assert_eq!(
+        template::render(
+            "{total} голосов, врать {lie_pct}%, {winner}/{runner_up}, {player_count} в зале",
+            &vars
+        ),
+        "10 голосов, врать 60%, врать/отложить, 12 в зале"
+    );
+    assert_eq!(
+        template::render("{{буквально}} {freeze}", &vars),
+        "{буквально} 1"
+    );
+
+    let empty = template::Vars {
+        counts: [0, 0, 0],
+        player_count: 0,
+    };
+    assert_eq!(template::render("{delay_pct}%", &empty), "0%");
+}
+
+#[test]
+fn template_errors_are_caught_at_deck_load() {
+    use template::TemplateError;
+    assert_eq!(template::validate("ok {total}"), Ok(()));
+    let unknown = template::validate("{nope}").unwrap_err();
+    assert!(matches!(&unknown, TemplateError::Unknown { name, .. } if name == "nope"));
+    assert!(unknown.to_string().contains("expected one of: total, lie"));
+    assert_eq!(
+        template::validate("a {total"),
+        Err(TemplateError::Unclosed { pos: 2 })
+    );
+    assert_eq!(
+        template::validate("a } b"),
+        Err(TemplateError::StrayClose { pos: 2 })
+    );
+
+    assert!(validate_deck(&build_situations()).is_ok());
+    let mut deck = test_situations();
+    deck[1].answers.insert(PAIR, vec!["{lie_pc}".to_string()]);
+    let err = validate_deck(&deck).unwrap_err();
+    assert!(err.contains("Вторая") && err.contains("lie_pc"), "{err}");
+}
+
+#[tokio::test]
+async fn show_renders_answer_template() {
+    let (app, state) = test_app();
+    lock(&state).situations[0]
+        .answers
+        .insert(PAIR, vec!["{lie} из {total} выбрали {winner}".to_string()]);
+    click(&app, "lie", 3).await;
+    click(&app, "delay", 1).await;
+    assert_eq!(
+        get_json(&app, "/admin/show").await["answer"],
+        "3 из 4 выбрали врать"
+    );
+}
+
+// ===================== Вопросы из зала =====================
+
+#[tokio::test]
+async fn question_moderation_and_promotion() {
+    let (app, _) = test_app();
+
+    let (status, _) = post_json(&app, "/api/questions", json!({ "text": "   " })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post_json(&app, "/api/questions", json!({ "text": " Где премия? " })).await;
+    assert_eq!(status, StatusCode::OK);
+    post_json(&app, "/api/questions", json!({ "text": "спам" })).await;
+
+    let list = get_json(&app, "/admin/questions").await;
+    assert_eq!(list[0]["text"], "Где премия?");
+    assert_eq!(list[0]["status"], "pending");
+
+    // нельзя вынести неодобренный
+    let (status, _) = post_json(&app, "/admin/questions/1/promote", json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, q) = post_json(
+        &app,
+        "/admin/questions/1",
+        json!({ "status": "approved", "text": "Где годовая премия?" }),
+    )
+    .await;
+    assert_eq!(q["status"], "approved");
+    assert_eq!(q["text"], "Где годовая премия?");
+    let (_, q) = post_json(&app, "/admin/questions/2", json!({ "status": "rejected" })).await;
+    assert_eq!(q["status"], "rejected");
+    let (status, _) = post_json(&app, "/admin/questions/9", json!({ "status": "approved" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    // отклонённая правка не трогает текст
+    let (status, _) = post_json(
+        &app,
+        "/admin/questions/1",
+        json!({ "status": "promoted", "text": "ПРАВКА" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        get_json(&app, "/admin/questions").await[0]["text"],
+        "Где годовая премия?"
+    );
+
+    // неполное, битое тело и пустые ответы не превращаются в свободное обсуждение
+    let (status, _) = post_json(
+        &app,
+        "/admin/questions/1/promote",
+        json!({ "answers": { "lie_delay": "A" } }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(
+        &app,
+        Request::post("/admin/questions/1/promote")
+            .header("content-type", "application/json")
+            .body(Body::from("{\"answers\":"))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post_json(
+        &app,
+        "/admin/questions/1/promote",
+        json!({ "answers": { "lie_delay": "в пути", "lie_freeze": " ", "delay_freeze": "" } }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        get_json(&app, "/admin/questions").await[0]["status"],
+        "approved"
+    );
+
+    let (status, body) = post_json(
+        &app,
+        "/admin/questions/1/promote",
+        json!({ "answers": { "lie_delay": "в пути", "lie_freeze": "её нет", "delay_freeze": "потом" } }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["index"], 1);
+    assert_eq!(
+        get_json(&app, "/admin/questions").await[0]["status"],
+        "promoted"
+    );
+
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(
+        get_json(&app, "/api/current").await["title"],
+        "Где годовая премия?"
+    );
+    click(&app, "lie", 1).await;
+    click(&app, "freeze", 2).await;
+    assert_eq!(get_json(&app, "/admin/show").await["answer"], "её нет");
+
+    // старая вторая ситуация съехала дальше
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/current").await["title"], "Вторая");
+}
+
+#[tokio::test]
+async fn promoted_question_without_answers_is_free_discussion() {
+    let (app, _) = test_app();
+    post_json(
+        &app,
+        "/api/questions",
+        json!({ "text": "Кто это придумал?" }),
+    )
+    .await;
+    post_json(&app, "/admin/questions/1", json!({ "status": "approved" })).await;
+    let (status, _) = send(
+        &app,
+        Request::post("/admin/questions/1/promote")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(
+        get_json(&app, "/admin/show").await["answer"],
+        FREE_DISCUSSION_ANSWER
+    );
+}
+
+#[tokio::test]
+async fn promoted_questions_survive_restart() {
+    let (app, state) = test_app();
+    post_json(&app, "/api/questions", json!({ "text": "Вопрос?" })).await;
+    post_json(&app, "/admin/questions/1", json!({ "status": "approved" })).await;
+    post_json(
+        &app,
+        "/admin/questions/1/promote",
+        json!({ "answers": { "lie_delay": "А", "lie_freeze": "Б", "delay_freeze": "В" } }),
+    )
+    .await;
+    post_empty(&app, "/admin/next").await;
+
+    let path = std::env::temp_dir().join(format!("corpoclicker-{}.json", std::process::id()));
+    persist::save(&lock(&state), &path).unwrap();
+
+    let mut restored = AppState::new(test_situations());
+    assert!(persist::load(&mut restored, &path).unwrap());
+    assert_eq!(restored.situations.len(), 3);
+    assert_eq!(restored.situations[restored.current_index].title, "Вопрос?");
+    assert_eq!(restored.situations[2].title, "Вторая");
+    assert_eq!(restored.questions.items[0].status, QuestionStatus::Promoted);
+
+    // вставку не повторить — прогресс сбрасываем, а вопрос снова можно промоутить
+    let mut changed = AppState::new(Vec::new());
+    assert!(!persist::load(&mut changed, &path).unwrap());
+    assert_eq!(changed.current_index, 0);
+    assert_eq!(changed.questions.items[0].status, QuestionStatus::Approved);
+    std::fs::remove_file(path).unwrap();
+}
+
+// ===================== Прогнозы =====================
+
+#[tokio::test]
+async fn predictions_score_points_and_fill_leaderboard() {
+    let (app, _) = test_app();
+
+    let (_, alice) = post_json(&app, "/api/players", json!({ "name": "Алиса" })).await;
+    let (_, bob) = post_json(&app, "/api/players", json!({ "name": " Боб " })).await;
+    assert_eq!(bob["name"], "Боб");
+    let (alice, bob) = (alice["id"].clone(), bob["id"].clone());
+
+    // пока режим выключен, прогнозы не принимаются
+    let (status, _) = post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": alice, "winner": "lie" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    post_json(&app, "/admin/prediction", json!({ "enabled": true })).await;
+    assert_eq!(
+        get_json(&app, "/api/current").await["prediction_mode"],
+        true
+    );
+
+    let (status, _) = post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": alice, "pair": ["freeze", "lie"], "winner": "freeze" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": bob, "pair": ["lie", "delay"] }),
+    )
+    .await;
+    let (status, _) = post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": "ghost", "winner": "lie" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    click(&app, "freeze", 3).await;
+    click(&app, "lie", 2).await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(
+        shown["leaderboard"],
+        json!([{ "name": "Алиса", "points": 3 }, { "name": "Боб", "points": 0 }])
+    );
+
+    // повторный показ очков не удваивает, а прогнозы уже закрыты
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["leaderboard"][0]["points"], 3);
+    let (status, _) = post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": bob, "winner": "lie" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    post_empty(&app, "/admin/next").await;
+    let me = get_json(&app, &format!("/api/players/{}", bob.as_str().unwrap())).await;
+    assert_eq!(me["prediction"], Value::Null);
+    post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": bob, "winner": "delay" }),
+    )
+    .await;
+    click(&app, "delay", 1).await;
+    get_json(&app, "/admin/show").await;
+
+    let board = get_json(&app, "/api/leaderboard").await;
+    assert_eq!(
+        board,
+        json!([{ "name": "Алиса", "points": 3 }, { "name": "Боб", "points": 1 }])
+    );
+}
+
+#[tokio::test]
+async fn reset_takes_back_points_and_reopens_predictions() {
+    let (app, _) = test_app();
+    let (_, alice) = post_json(&app, "/api/players", json!({ "name": "Алиса" })).await;
+    let alice = alice["id"].clone();
+    post_json(&app, "/admin/prediction", json!({ "enabled": true })).await;
+    post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": alice, "pair": ["lie", "delay"] }),
+    )
+    .await;
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    get_json(&app, "/admin/show").await;
+    assert_eq!(get_json(&app, "/api/leaderboard").await[0]["points"], 2);
+
+    post_empty(&app, "/admin/reset").await;
+    assert_eq!(get_json(&app, "/api/leaderboard").await[0]["points"], 0);
+    let (status, _) = post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": alice, "pair": ["lie", "freeze"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    click(&app, "lie", 2).await;
+    click(&app, "freeze", 1).await;
+    get_json(&app, "/admin/show").await;
+    assert_eq!(get_json(&app, "/api/leaderboard").await[0]["points"], 2);
+}
+
+#[tokio::test]
+async fn player_registrations_are_capped() {
+    let (app, state) = test_app();
+    for i in 0..players::MAX_PLAYERS {
+        lock(&state)
+            .players
+            .register(&format!("Игрок {i}"), None)
+            .unwrap();
+    }
+    let (status, body) = post_json(&app, "/api/players", json!({ "name": "Лишний" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert_eq!(lock(&state).players.len(), players::MAX_PLAYERS);
+}
+
+#[tokio::test]
+async fn leaderboard_is_absent_without_prediction_mode() {
+    let (app, _) = test_app();
+    let shown = get_json(&app, "/admin/show").await;
+    assert!(shown.get("leaderboard").is_none());
+}
+
+// ===================== Команды =====================
+
+#[tokio::test]
+async fn team_mode_tracks_counts_per_team() {
+    let (app, _) = test_app();
+    post_json(
+        &app,
+        "/admin/teams",
+        json!({ "teams": ["Разработка", " ", "Менеджмент"] }),
+    )
+    .await;
+    assert_eq!(
+        get_json(&app, "/api/current").await["teams"],
+        json!(["Разработка", "Менеджмент"])
+    );
+
+    let (_, dev) = post_json(&app, "/api/players", json!({ "name": "Дев", "team": 0 })).await;
+    // без выбора — в самую маленькую команду
+    let (_, boss) = post_json(&app, "/api/players", json!({ "name": "Босс" })).await;
+    assert_eq!(boss["team"], 1);
+    let (status, _) = post_json(&app, "/api/players", json!({ "name": "Х", "team": 5 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    for r in ["lie", "freeze", "freeze"] {
+        post_json(
+            &app,
+            "/api/click",
+            json!({ "reaction": r, "player": dev["id"] }),
+        )
+        .await;
+    }
+    for r in ["delay", "delay", "lie"] {
+        post_json(
+            &app,
+            "/api/click",
+            json!({ "reaction": r, "player": boss["id"] }),
+        )
+        .await;
+    }
+    // анонимный клик идёт только в общий счёт
+    click(&app, "delay", 1).await;
+
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["counts"], json!([2, 3, 2]));
+    assert_eq!(shown["answer"], "врать+отложить");
+    assert_eq!(
+        shown["teams"],
+        json!([
+            { "name": "Разработка", "counts": [1, 0, 2], "pair": ["lie", "freeze"], "answer": "врать+заморозить" },
+            { "name": "Менеджмент", "counts": [1, 2, 0], "pair": ["lie", "delay"], "answer": "врать+отложить" }
+        ])
+    );
+
+    post_empty(&app, "/admin/next").await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["teams"][0]["counts"], json!([0, 0, 0]));
+}
+
+#[tokio::test]
+async fn changing_teams_reassigns_players() {
+    let (app, _) = test_app();
+    let (_, early) = post_json(&app, "/api/players", json!({ "name": "Ранний" })).await;
+    assert_eq!(early["team"], Value::Null);
+    let player = |p: &Value| format!("/api/players/{}", p["id"].as_str().unwrap());
+
+    // пришедшие до команд тоже попадают в команду
+    post_json(&app, "/admin/teams", json!({ "teams": ["А", "Б"] })).await;
+    let (_, late) = post_json(&app, "/api/players", json!({ "name": "Поздний" })).await;
+    let mut teams = vec![
+        get_json(&app, &player(&early)).await["team"]
+            .as_u64()
+            .unwrap(),
+        late["team"].as_u64().unwrap(),
+    ];
+    teams.sort();
+    assert_eq!(teams, [0, 1]);
+
+    // другой список: индексы старого не переживают смену
+    post_json(&app, "/admin/teams", json!({ "teams": ["В"] })).await;
+    assert_eq!(get_json(&app, &player(&late)).await["team"], 0);
+    post_json(
+        &app,
+        "/api/click",
+        json!({ "reaction": "lie", "player": late["id"] }),
+    )
+    .await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["teams"][0]["counts"], json!([1, 0, 0]));
+
+    post_json(&app, "/admin/teams", json!({ "teams": [] })).await;
+    assert_eq!(get_json(&app, &player(&late)).await["team"], Value::Null);
+}
+
+#[tokio::test]
+async fn ranked_voting_has_no_team_results() {
+    let (app, _) = test_app();
+    post_json(&app, "/admin/teams", json!({ "teams": ["А", "Б"] })).await;
+    post_json(&app, "/admin/voting", json!({ "mode": "borda" })).await;
+    post_json(
+        &app,
+        "/api/rank",
+        json!({ "ranking": ["lie", "delay", "freeze"] }),
+    )
+    .await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert!(shown.get("teams").is_none());
+}
+
+// ===================== Эмодзи =====================
+
+#[test]
+fn emoji_stream_aggregates_per_second() {
+    let mut stream = EmojiStream::default();
+    stream.push_at(5, Emoji::Laugh);
+    stream.push_at(5, Emoji::Laugh);
+    stream.push_at(5, Emoji::Fire);
+    stream.push_at(6, Emoji::Facepalm);
+
+    // секунда 6 ещё идёт — её не отдаём
+    let closed = stream.since_at(6, None);
+    assert_eq!(closed.len(), 1);
+    assert_eq!((closed[0].second, closed[0].counts), (5, [0, 2, 1]));
+    assert!(stream.since_at(7, Some(5)).iter().all(|b| b.second == 6));
+
+    // старая история выбрасывается
+    stream.push_at(100, Emoji::Fire);
+    assert_eq!(stream.since_at(101, None).len(), 1);
+}
+
+#[tokio::test]
+async fn emoji_only_after_reveal_and_not_counted_as_votes() {
+    let (app, state) = test_app();
+    let (status, _) = post_json(&app, "/api/emoji", json!({ "emoji": "fire" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    click(&app, "lie", 1).await;
+    get_json(&app, "/admin/show").await;
+    for e in ["fire", "laugh", "fire"] {
+        let (status, _) = post_json(&app, "/api/emoji", json!({ "emoji": e })).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = post_json(&app, "/api/emoji", json!({ "emoji": "poop" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    assert_eq!(lock(&state).counts, [1, 0, 0]);
+    let now = lock(&state).emoji.now();
+    let pending = lock(&state).emoji.since_at(now + 1, None);
+    assert_eq!(pending.iter().map(|b| b.counts[2]).sum::<u32>(), 2);
+
+    let polled = get_json(&app, "/api/emoji?since=0").await;
+    assert!(polled["buckets"].is_array());
+}
+
+// ===================== Ветвления =====================
+
+fn node(id: &str, branches: &[((Reaction, Reaction), &str)], branch_only: bool) -> Situation {
+    Situation {
+        title: id.to_string(),
+        id: Some(id.to_string()),
+        answers: make_answers("ЛД", "ЛЗ", "ОЗ"),
+        branches: branches.iter().map(|(p, t)| (*p, t.to_string())).collect(),
+        branch_only,
+        ..Default::default()
+    }
+}
+
+const LF: (Reaction, Reaction) = (Reaction::Lie, Reaction::Freeze);
+const DF: (Reaction, Reaction) = (Reaction::Delay, Reaction::Freeze);
+
+#[tokio::test]
+async fn next_follows_branch_for_winning_pair() {
+    let (app, state) = test_app();
+    lock(&state).situations = vec![
+        node("start", &[(LF, "leak")], false),
+        node("leak", &[], true),
+        node("end", &[], false),
+    ];
+
+    // без ветки «Дальше» пропускает branch_only
+    click(&app, "delay", 2).await;
+    click(&app, "lie", 1).await;
+    let up = get_json(&app, "/admin/upcoming").await;
+    assert_eq!(
+        (up["title"].as_str(), up["via_branch"].as_bool()),
+        (Some("end"), Some(false))
+    );
+
+    // показанная пара фиксирует ветку, даже если клики потом поменялись
+    click(&app, "lie", 2).await;
+    click(&app, "freeze", 3).await;
+    get_json(&app, "/admin/show").await;
+    click(&app, "delay", 5).await;
+    let up = get_json(&app, "/admin/upcoming").await;
+    assert_eq!(up["title"], "leak");
+    assert_eq!(up["via_branch"], true);
+    assert_eq!(up["pair"], json!(["lie", "freeze"]));
+
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/current").await["title"], "leak");
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/current").await["title"], "end");
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/current").await["title"], "start");
+}
+
+#[test]
+fn deck_validator_checks_branches() {
+    let ok = [
+        node("a", &[(LF, "side")], false),
+        node("side", &[], true),
+        node("b", &[], false),
+    ];
+    assert_eq!(branching::validate(&ok), Ok(()));
+
+    let unknown = [node("a", &[(LF, "nope")], false)];
+    assert!(branching::validate(&unknown)
+        .unwrap_err()
+        .contains("unknown id"));
+
+    let dup = [node("a", &[], false), node("a", &[], false)];
+    assert!(branching::validate(&dup).unwrap_err().contains("duplicate"));
+
+    let orphan = [
+        node("a", &[], false),
+        node("lost", &[], true),
+        node("b", &[], false),
+    ];
+    assert!(branching::validate(&orphan)
+        .unwrap_err()
+        .contains("\"lost\" is unreachable"));
+
+    // ветки на все пары — линейного перехода нет, «b» недостижима
+    let full = [
+        node("a", &[(LF, "c"), (DF, "c"), (PAIR, "c")], false),
+        node("b", &[], false),
+        node("c", &[], false),
+    ];
+    assert!(branching::validate(&full)
+        .unwrap_err()
+        .contains("\"b\" is unreachable"));
+
+    let cycle = [node("a", &[], false), node("b", &[(LF, "a")], false)];
+    assert!(branching::validate(&cycle).unwrap_err().contains("cycle"));
+}
+
+// ===================== Шкалы компании =====================
+
+fn meters_app() -> (Router, Shared) {
+    let (app, state) = test_app();
+    {
+        let mut st = lock(&state);
+        for s in &mut st.situations {
+            s.effects = make_effects(&[("trust", -30)], &[("trust", 10)], &[("budget", -5)]);
+        }
+        st.meters = Meters::new(meters::Config {
+            meters: vec![
+                MeterDef {
+                    id: "trust".to_string(),
+                    label: "Доверие".to_string(),
+                    start: 50,
+                    min: 0,
+                    max: 100,
+                },
+                MeterDef {
+                    id: "budget".to_string(),
+                    label: "Бюджет".to_string(),
+                    start: 50,
+                    min: 0,
+                    max: 100,
+                },
+            ],
+            endings: vec![Ending {
+                meter: "trust".to_string(),
+                bound: Bound::AtMost(0),
+                title: "Развалились".to_string(),
+                text: "Конец".to_string(),
+            }],
+        });
+    }
+    (app, state)
+}
+
+#[tokio::test]
+async fn meters_accumulate_and_end_the_game() {
+    let (app, state) = meters_app();
+
+    // врать + отложить: доверие 50 -> 20
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(
+        shown["changes"],
+        json!([{ "meter": "trust", "label": "Доверие", "delta": -30 }])
+    );
+    assert!(shown.get("ending").is_none());
+
+    // повторный показ не применяет эффект второй раз, сброс откатывает его
+    get_json(&app, "/admin/show").await;
+    assert_eq!(lock(&state).meters.value("trust"), Some(20));
+    post_empty(&app, "/admin/reset").await;
+    assert_eq!(lock(&state).meters.value("trust"), Some(50));
+
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    get_json(&app, "/admin/show").await;
+    post_empty(&app, "/admin/next").await;
+    let cur = get_json(&app, "/api/current").await;
+    assert_eq!(cur["meters"][0]["value"], 20);
+    assert_eq!(cur["ending"], Value::Null);
+
+    // ещё раз -30, упор в 0 — концовка
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["changes"][0]["delta"], -20);
+    assert_eq!(shown["ending"]["title"], "Развалились");
+    assert_eq!(
+        get_json(&app, "/api/present").await["ending"]["title"],
+        "Развалились"
+    );
+    let (status, body) = send(
+        &app,
+        Request::post("/admin/next").body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("Развалились"));
+
+    post_empty(&app, "/admin/restart").await;
+    let cur = get_json(&app, "/api/current").await;
+    assert_eq!(cur["title"], "Первая");
+    assert_eq!(cur["meters"][0]["value"], 50);
+    assert_eq!(cur["ending"], Value::Null);
+}
+
+#[test]
+fn meters_validation() {
+    assert_eq!(
+        meters::validate(&build_meters(), &build_situations()),
+        Ok(())
+    );
+
+    let mut deck = test_situations();
+    deck[1].effects = make_effects(&[("karma", 1)], &[], &[]);
+    let err = meters::validate(&build_meters(), &deck).unwrap_err();
+    assert!(err.contains("unknown meter \"karma\""), "{err}");
+
+    let mut config = build_meters();
+    config.meters[0].start = 500;
+    assert!(meters::validate(&config, &[])
+        .unwrap_err()
+        .contains("outside"));
+}
+
+// ===================== Ранжированное голосование =====================
+
+fn ballots(list: &[&[Reaction]]) -> Ballots {
+    let mut b = Ballots::default();
+    for ranking in list {
+        b.cast(ranking, None).unwrap();
+    }
+    b
+}
+
+#[test]
+fn borda_and_pairwise_tally() {
+    use Reaction::*;
+    let b = ballots(&[
+        &[Lie, Freeze, Delay],
+        &[Lie, Freeze, Delay],
+        &[Lie, Freeze, Delay],
+        &[Delay, Freeze, Lie],
+        &[Delay, Freeze],
+    ]);
+
+    let borda = b.tally(VotingMode::Borda);
+    assert_eq!(borda.ballots, 5);
+    assert_eq!(borda.scores, [6, 4, 5]);
+    assert_eq!(borda.order, [Lie, Freeze, Delay]);
+    assert_eq!(borda.positions[2], [0, 5, 0]);
+    // неназванная реакция проигрывает всем названным
+    assert_eq!(borda.pairwise[1][0], 2);
+    assert_eq!(borda.pairwise[2][0], 2);
+    assert_eq!(borda.pairwise[0][1], 3);
+
+    let pairwise = b.tally(VotingMode::Pairwise);
+    assert_eq!(pairwise.scores, [4, 0, 2]);
+    assert_eq!(pairwise.order, [Lie, Freeze, Delay]);
+}
+
+#[test]
+fn ballots_reject_bad_rankings_and_replace_player_vote() {
+    use Reaction::*;
+    let mut b = Ballots::default();
+    assert!(b.cast(&[Lie], None).is_err());
+    assert!(b.cast(&[Lie, Lie], None).is_err());
+    b.cast(&[Lie, Delay], Some("p1")).unwrap();
+    b.cast(&[Freeze, Delay], Some("p1")).unwrap();
+    assert_eq!(b.len(), 1);
+    assert_eq!(b.tally(VotingMode::Borda).scores, [0, 1, 2]);
+}
+
+#[tokio::test]
+async fn ranked_mode_picks_pair_by_scores() {
+    let (app, _) = test_app();
+
+    let (status, _) = post_json(&app, "/api/rank", json!({ "ranking": ["lie", "delay"] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = post_json(&app, "/admin/voting", json!({ "mode": "borda" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(get_json(&app, "/api/current").await["voting"], "borda");
+
+    for _ in 0..3 {
+        let (status, _) = post_json(
+            &app,
+            "/api/rank",
+            json!({ "ranking": ["lie", "freeze", "delay"] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    for _ in 0..2 {
+        post_json(&app, "/api/rank", json!({ "ranking": ["delay", "freeze"] })).await;
+    }
+    let (status, _) = post_json(&app, "/api/rank", json!({ "ranking": ["lie", "nope"] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(get_json(&app, "/api/present").await["ballots"], 5);
+
+    // по первым местам лидировали бы врать + отложить, по Борда — врать + заморозить
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], "врать+заморозить");
+    assert_eq!(shown["counts"], json!([6, 4, 5]));
+    assert_eq!(shown["ranking"]["method"], "borda");
+    assert_eq!(shown["ranking"]["order"], json!(["lie", "freeze", "delay"]));
+
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/present").await["ballots"], 0);
+}
+
+// ===================== Стратегии выбора пары =====================
+
+#[test]
+fn winner_strategies_select_pairs() {
+    use winner::{AdminOverride, LeastPopular, TopTwo, WeightedRandom, WinnerStrategy};
+    use Reaction::*;
+
+    assert_eq!(TopTwo.select(&[5, 1, 3]), (Lie, Freeze));
+    assert_eq!(LeastPopular.select(&[5, 1, 3]), (Delay, Freeze));
+    assert_eq!(LeastPopular.select(&[0, 0, 0]), (Lie, Delay));
+    let fixed = AdminOverride {
+        pair: (Delay, Freeze),
+    };
+    assert_eq!(fixed.select(&[9, 0, 0]), (Delay, Freeze));
+
+    // билет 0 — первая реакция с голосами, билет за её голосами — следующая
+    let first = WeightedRandom { roll: || 0 };
+    assert_eq!(first.select(&[0, 4, 6]), (Delay, Freeze));
+    let late = WeightedRandom { roll: || 9 };
+    assert_eq!(late.select(&[0, 4, 6]), (Freeze, Delay));
+    // реакция без голосов не выигрывает, пока есть другие
+    assert_eq!(late.select(&[0, 0, 6]).0, Freeze);
+    // подсказка до показа не бросает кубик
+    assert_eq!(late.expected(&[1, 4, 6]), (Freeze, Delay));
+}
+
+#[tokio::test]
+async fn room_and_situation_choose_winner_strategy() {
+    let (app, state) = test_app();
+    click(&app, "lie", 3).await;
+    click(&app, "delay", 2).await;
+
+    let (status, _) = post_json(
+        &app,
+        "/admin/winner",
+        json!({ "strategy": "least-popular" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], "отложить+заморозить");
+    assert_eq!(shown["selection"], json!({ "strategy": "least-popular" }));
+
+    let (status, _) = post_json(
+        &app,
+        "/admin/winner",
+        json!({ "strategy": "override", "pair": ["freeze", "lie"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        get_json(&app, "/admin/show").await["answer"],
+        "врать+заморозить"
+    );
+
+    let (status, _) = post_json(
+        &app,
+        "/admin/winner",
+        json!({ "strategy": "override", "pair": ["lie", "lie"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // стратегия ситуации важнее комнатной
+    lock(&state).situations[0].winner = Some(WinnerRule::TopTwo);
+    assert_eq!(
+        get_json(&app, "/admin/show").await["answer"],
+        "врать+отложить"
+    );
+}
+
+// ===================== Правила ответов =====================
+
+fn vars(counts: [u64; 3]) -> template::Vars {
+    template::Vars {
+        counts,
+        player_count: 4,
+    }
+}
+
+#[test]
+fn rules_evaluate_shares_counts_and_logic() {
+    let on = |src: &str, counts| rules::compile(src).unwrap().eval(&vars(counts));
+
+    assert!(on("freeze > 70%", [1, 1, 8]));
+    assert!(!on("freeze > 70%", [2, 1, 7]));
+    assert!(on("freeze >= 70%", [2, 1, 7]));
+    // без голосов доли не определены
+    assert!(!on("freeze < 70%", [0, 0, 0]));
+    assert!(on("total < 5", [1, 1, 2]));
+    assert!(on("lie within 10% of delay", [45, 40, 15]));
+    assert!(!on("lie within 10% of delay", [50, 35, 15]));
+    assert!(on("lie within 2 of delay", [5, 3, 0]));
+    assert!(on("player_count == 4 and not (lie > delay)", [1, 1, 0]));
+    assert!(on("total == 0 or freeze != 0", [1, 0, 1]));
+    assert!(on("not total > 1 and lie == 1", [1, 0, 0]));
+}
+
+#[test]
+fn rules_are_type_checked() {
+    let msg = |src: &str| rules::compile(src).unwrap_err().to_string();
+
+    assert!(rules::compile("(lie > 50% or total >= 10) and delay < freeze").is_ok());
+    assert!(msg("total > 70%").contains("only lie, delay and freeze have a share"));
+    assert!(msg("70% < 80%").contains("two constants"));
+    assert!(msg("total within 10% of lie").contains("reaction shares"));
+    assert!(msg("lie within delay of freeze").contains("tolerance"));
+    assert!(msg("karma > 1").contains("unknown variable 'karma'"));
+    assert!(msg("lie > 150%").contains("above 100"));
+    assert!(msg("(lie > 1").contains("expected ')'"));
+    assert!(msg("lie 5").contains("expected a comparison"));
+    assert!(msg("lie > 1 delay").contains("unexpected token"));
+    assert!(msg("lie ≈ 1").contains("unexpected character"));
+    assert!(msg("").contains("empty"));
+}
+
+#[tokio::test]
+async fn rules_win_over_pairs_and_break_deck_validation() {
+    let (app, state) = test_app();
+    lock(&state).situations[0].rules = make_rules(&[
+        ("total < 2", "Тишина в зале"),
+        ("freeze > 60%", "Заморозили {freeze_pct}%"),
+    ]);
+
+    click(&app, "lie", 1).await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], "Тишина в зале");
+    assert_eq!(shown["rule"], "total < 2");
+
+    click(&app, "freeze", 4).await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], "Заморозили 80%");
+
+    click(&app, "lie", 3).await;
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], "врать+заморозить");
+    assert!(shown.get("rule").is_none());
+
+    let mut deck = test_situations();
+    deck[1].rules = make_rules(&[("players > 3", "x")]);
+    let err = validate_deck(&deck).unwrap_err();
+    assert!(
+        err.contains("situation #1") && err.contains("unknown variable 'players'"),
+        "{err}"
+    );
+}
+
+// ===================== Правка ведущего при показе =====================
+
+#[tokio::test]
+async fn admin_show_override_forces_pair_variant_or_text() {
+    let (app, state) = test_app();
+    lock(&state).situations[0].answers = make_answer_pools(&["ЛД-1", "ЛД-2"], &["ЛЗ"], &["ОЗ"]);
+    lock(&state).situations[0].rules = make_rules(&[("total >= 0", "по правилу")]);
+    click(&app, "lie", 3).await;
+    click(&app, "delay", 2).await;
+
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], "по правилу");
+    assert_eq!(shown["overridden"], false);
+
+    // пара ведущего, правила колоды пропускаются
+    let (status, shown) =
+        post_json(&app, "/admin/show", json!({ "pair": ["freeze", "delay"] })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(shown["answer"], "ОЗ");
+    assert_eq!(shown["pair"], json!(["delay", "freeze"]));
+    assert_eq!(shown["overridden"], true);
+    assert!(shown.get("rule").is_none());
+
+    let (_, shown) = post_json(&app, "/admin/show", json!({ "variant": 1 })).await;
+    assert_eq!(
+        (shown["answer"].as_str(), shown["variant"].as_u64()),
+        (Some("ЛД-2"), Some(1))
+    );
+
+    let (_, shown) = post_json(
+        &app,
+        "/admin/show",
+        json!({ "pair": ["lie", "freeze"], "answer": "  Всего {total}, без комментариев " }),
+    )
+    .await;
+    assert_eq!(shown["answer"], "Всего 5, без комментариев");
+    assert_eq!(shown["pair"], json!(["lie", "freeze"]));
+    assert_eq!(get_json(&app, "/api/result").await["overridden"], true);
+
+    // пустое тело — обычный показ
+    let (_, shown) = post_json(&app, "/admin/show", json!({})).await;
+    assert_eq!(shown["overridden"], false);
+
+    for bad in [
+        json!({ "variant": 2 }),
+        json!({ "pair": ["lie", "lie"] }),
+        json!({ "answer": "   " }),
+        json!({ "answer": "{nope}" }),
+        json!({ "pair": ["lie", "nope"] }),
+    ] {
+        let (status, _) = post_json(&app, "/admin/show", bad.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{bad}");
+    }
+
+    // прогноз сверяется с выбором зала, а не с парой ведущего
+    let (_, alice) = post_json(&app, "/api/players", json!({ "name": "Алиса" })).await;
+    post_json(&app, "/admin/prediction", json!({ "enabled": true })).await;
+    post_empty(&app, "/admin/reset").await;
+    post_json(
+        &app,
+        "/api/predict",
+        json!({ "player": alice["id"], "pair": ["lie", "delay"] }),
+    )
+    .await;
+    click(&app, "lie", 3).await;
+    click(&app, "delay", 2).await;
+    let (_, shown) = post_json(&app, "/admin/show", json!({ "pair": ["lie", "freeze"] })).await;
+    assert_eq!(shown["leaderboard"][0]["points"], 2);
+}
+
+// ===================== Черновик ответа =====================
+
+#[tokio::test]
+async fn preview_matches_show_without_publishing() {
+    let (app, state) = meters_app();
+    lock(&state).situations[0].answers = make_answer_pools(&["ЛД-1", "ЛД-2"], &["ЛЗ"], &["ОЗ"]);
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+
+    // черновик повторяется и ничего не двигает
+    let draft = get_json(&app, "/admin/preview").await;
+    assert_eq!(get_json(&app, "/admin/preview").await, draft);
+    assert_eq!(draft["answer"], "ЛД-1");
+    assert_eq!(draft["variants"], 2);
+    assert_eq!(draft["changes"][0]["delta"], -30);
+    assert_eq!(draft["shown_version"], Value::Null);
+    assert_eq!(get_json(&app, "/api/result").await, Value::Null);
+    assert_eq!(lock(&state).meters.value("trust"), Some(50));
+    assert_eq!(lock(&state).result_version, 0);
+
+    // показ выдаёт ровно черновик, следующий черновик — уже второй вариант
+    let shown = get_json(&app, "/admin/show").await;
+    assert_eq!(shown["answer"], draft["answer"]);
+    let next = get_json(&app, "/admin/preview").await;
+    assert_eq!(next["answer"], "ЛД-2");
+    assert_eq!(next["shown_version"], 1);
+    // повторный показ пересчитает шкалы от начала ситуации
+    assert_eq!(next["changes"][0]["delta"], -30);
+
+    let (status, draft) = post_json(&app, "/admin/preview", json!({ "answer": "Свой" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        (draft["answer"].as_str(), draft["overridden"].as_bool()),
+        (Some("Свой"), Some(true))
+    );
+    assert_eq!(get_json(&app, "/api/result").await["answer"], "ЛД-1");
+    assert_eq!(draft["binding"], true);
+}
+
+#[tokio::test]
+async fn preview_of_random_strategies_is_expected_and_not_binding() {
+    let (app, state) = test_app();
+    {
+        let mut st = lock(&state);
+        st.situations[0].answers = make_answer_pools(&["ЛД-1", "ЛД-2"], &["ЛЗ"], &["ОЗ"]);
+        st.winner = WinnerRule::WeightedRandom;
+        st.variants.strategy = VariantStrategy::Random;
+    }
+    click(&app, "lie", 3).await;
+    click(&app, "delay", 2).await;
+    click(&app, "freeze", 1).await;
+
+    // кубик не бросаем: ожидаемая пара и стабильный вариант
+    let draft = get_json(&app, "/admin/preview").await;
+    assert_eq!(draft["pair"], json!(["lie", "delay"]));
+    assert_eq!(draft["binding"], false);
+    for _ in 0..10 {
+        assert_eq!(get_json(&app, "/admin/preview").await, draft);
+    }
+
+    // пара и вариант заданы ведущим — случаю выбирать нечего
+    let (_, draft) = post_json(
+        &app,
+        "/admin/preview",
+        json!({ "pair": ["lie", "delay"], "variant": 1 }),
+    )
+    .await;
+    assert_eq!(draft["binding"], true);
+}
+
+// ===================== Все ответы =====================
+
+#[tokio::test]
+async fn reveal_all_publishes_every_pair_with_shares() {
+    let (app, state) = test_app();
+    let (status, _) = send(
+        &app,
+        Request::post("/admin/reveal-all")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    click(&app, "lie", 3).await;
+    click(&app, "delay", 1).await;
+    let shown = get_json(&app, "/admin/show").await;
+    // клики после показа на доли не влияют
+    click(&app, "freeze", 10).await;
+
+    let comparison = post_empty(&app, "/admin/reveal-all").await;
+    let entries: Vec<_> = comparison["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            (
+                e["answers"][0].as_str().unwrap(),
+                e["share"].as_u64().unwrap(),
+                e["chosen"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            ("врать+отложить", 100, true),
+            ("врать+заморозить", 75, false),
+            ("отложить+заморозить", 25, false),
+        ]
+    );
+
+    // игроки видят сравнение вместе с ответом, у сравнения своя версия
+    let result = get_json(&app, "/api/result").await;
+    assert_eq!(result["version"], shown["version"]);
+    assert_eq!(result["comparison"]["version"], comparison["version"]);
+    assert!(comparison["version"].as_u64() > shown["version"].as_u64());
+
+    post_empty(&app, "/admin/next").await;
+    assert_eq!(get_json(&app, "/api/result").await, Value::Null);
+    assert!(lock(&state).last_result.is_none());
+}
+
+// ===================== Постепенный показ =====================
+
+#[test]
+fn reveal_stages_follow_the_clock_and_skip() {
+    assert_eq!(
+        reveal::Config::from_str("bars=2, countdown=0.5"),
+        Ok(reveal::Config {
+            bars_ms: 2000,
+            countdown_ms: 500
+        })
+    );
+    assert_eq!(
+        reveal::Config::from_str("off"),
+        Ok(reveal::Config::default())
+    );
+    assert!(reveal::Config::from_str("drums=3").is_err());
+    assert!(reveal::Config::from_str("bars=-1").is_err());
+
+    let config = reveal::Config {
+        bars_ms: 2000,
+        countdown_ms: 3000,
+    };
+    let t0 = Instant::now();
+    let at = |ms| t0 + Duration::from_millis(ms);
+    let status = |stage, left_ms| reveal::Status { stage, left_ms };
+    let mut r = Reveal::start(t0);
+    assert_eq!(
+        r.status(&config, at(500)),
+        status(reveal::Stage::Bars, 1500)
+    );
+    assert_eq!(
+        r.status(&config, at(2000)),
+        status(reveal::Stage::Countdown, 3000)
+    );
+    assert_eq!(r.status(&config, at(5000)), reveal::Status::DONE);
+
+    // промотка — в начало следующего этапа, дальше часы идут как шли
+    assert_eq!(
+        r.skip(&config, at(100)),
+        status(reveal::Stage::Countdown, 3000)
+    );
+    assert_eq!(
+        r.status(&config, at(1100)),
+        status(reveal::Stage::Countdown, 2000)
+    );
+    assert_eq!(r.skip(&config, at(1100)), reveal::Status::DONE);
+    assert_eq!(r.skip(&config, at(1100)), reveal::Status::DONE);
+
+    // без этапов ответ сразу
+    let r = Reveal::start(t0);
+    assert_eq!(
+        r.status(&reveal::Config::default(), t0),
+        reveal::Status::DONE
+    );
+}
+
+#[tokio::test]
+async fn staged_reveal_hides_answer_until_the_last_stage() {
+    let (app, state) = meters_app();
+    lock(&state).reveal_config = reveal::Config {
+        bars_ms: 60_000,
+        countdown_ms: 60_000,
+    };
+    let (status, _) = send(
+        &app,
+        Request::post("/admin/reveal/skip")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    // ведущий видит ответ сразу
+    assert_eq!(
+        get_json(&app, "/admin/show").await["answer"],
+        "врать+отложить"
+    );
+
+    let result = get_json(&app, "/api/result").await;
+    assert_eq!(result["reveal"]["stage"], "bars");
+    assert_eq!(result["counts"], json!([2, 1, 0]));
+    assert_eq!(result["answer"], "");
+    // выбранная пара и всё, что про неё говорит, — только с ответом
+    for hidden in [
+        "changes",
+        "pair",
+        "variant",
+        "variants",
+        "selection",
+        "overridden",
+    ] {
+        assert!(result.get(hidden).is_none(), "{hidden}");
+    }
+    let present = get_json(&app, "/api/present").await;
+    assert!(present["result"].get("pair").is_none());
+    // шкалы двинутся вместе с ответом
+    assert_eq!(
+        get_json(&app, "/api/current").await["meters"][0]["value"],
+        50
+    );
+    assert_eq!(
+        get_json(&app, "/api/present").await["result"]["reveal"]["stage"],
+        "bars"
+    );
+
+    let skipped = post_empty(&app, "/admin/reveal/skip").await;
+    assert_eq!(skipped["stage"], "countdown");
+    assert_eq!(get_json(&app, "/api/result").await["answer"], "");
+
+    assert_eq!(
+        post_empty(&app, "/admin/reveal/skip").await["stage"],
+        "answer"
+    );
+    let result = get_json(&app, "/api/result").await;
+    assert_eq!(result["answer"], "врать+отложить");
+    assert_eq!(result["pair"], json!(["lie", "delay"]));
+    assert_eq!(result["changes"][0]["delta"], -30);
+    assert_eq!(
+        get_json(&app, "/api/current").await["meters"][0]["value"],
+        20
+    );
+}
+
+// ===================== Шпаргалка ведущего =====================
+
+#[tokio::test]
+async fn speaker_notes_stay_in_admin() {
+    let (app, state) = test_app();
+    lock(&state).situations[0].script = make_script("СЕКРЕТ", &["Вопрос залу"], 3);
+
+    let script = get_json(&app, "/admin/script").await;
+    assert_eq!(script["title"], "Первая");
+    assert_eq!(
+        (script["index"].as_u64(), script["total"].as_u64()),
+        (Some(0), Some(2))
+    );
+    assert_eq!(script["notes"], "СЕКРЕТ");
+    assert_eq!(script["prompts"], json!(["Вопрос залу"]));
+    assert_eq!(script["duration_secs"], 180);
+
+    // игрокам и проектору заметки не уходят ни до показа, ни после
+    click(&app, "lie", 1).await;
+    get_json(&app, "/admin/show").await;
+    for uri in ["/api/current", "/api/result", "/api/present"] {
+        let body = get_json(&app, uri).await.to_string();
+        assert!(
+            !body.contains("СЕКРЕТ") && !body.contains("Вопрос залу"),
+            "{uri}: {body}"
+        );
+    }
+
+    post_empty(&app, "/admin/next").await;
+    let script = get_json(&app, "/admin/script").await;
+    assert_eq!(
+        (script["notes"].as_str(), script["duration_secs"].is_null()),
+        (Some(""), true)
+    );
+
+    let mut deck = test_situations();
+    deck[1].script.duration_secs = Some(0);
+    assert!(validate_deck(&deck).unwrap_err().contains("duration"));
+}
+
+// ===================== Обсуждение и итоги =====================
+
+#[test]
+fn discussion_starts_after_the_answer_opens() {
+    let t0 = Instant::now();
+    let at = |secs| t0 + Duration::from_secs(secs);
+    let d = Discussion::start(t0, 60);
+
+    // ответ ещё раскрывается пять секунд — таймер стоит
+    let status = d.status(Some(at(5)), at(2));
+    assert!(status.pending && !status.over);
+    assert_eq!(status.left_ms, 60_000);
+
+    assert_eq!(d.status(Some(at(5)), at(35)).left_ms, 30_000);
+    assert!(d.status(None, at(61)).over);
+    assert_eq!(d.spent_secs(Some(at(5)), at(25)), 20);
+    assert_eq!(d.spent_secs(None, at(500)), 60);
+}
+
+#[tokio::test]
+async fn discussion_notes_and_actions_land_in_export() {
+    let (app, state) = test_app();
+    let (status, _) = post_json(&app, "/admin/discussion", json!({ "secs": 60 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // заметка до показа тоже ложится на текущую ситуацию
+    let (status, note) = post_json(&app, "/admin/notes", json!({ "text": "  Все молчат  " })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        (note["kind"].as_str(), note["text"].as_str()),
+        (Some("note"), Some("Все молчат"))
+    );
+    let (status, _) = post_json(&app, "/admin/notes", json!({ "text": "  " })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    get_json(&app, "/admin/show").await;
+    assert!(get_json(&app, "/api/current").await["discussion"].is_null());
+
+    let (status, _) = post_json(&app, "/admin/discussion", json!({ "secs": 0 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let started = post_empty(&app, "/admin/discussion").await;
+    assert_eq!(started["secs"], 180);
+    let (_, started) = post_json(&app, "/admin/discussion", json!({ "secs": 90 })).await;
+    assert_eq!(
+        (started["secs"].as_u64(), started["pending"].as_bool()),
+        (Some(90), Some(false))
+    );
+    assert_eq!(
+        get_json(&app, "/api/current").await["discussion"]["secs"],
+        90
+    );
+    assert_eq!(
+        get_json(&app, "/api/present").await["discussion"]["over"],
+        false
+    );
+
+    let (_, action) = post_json(
+        &app,
+        "/admin/notes",
+        json!({ "kind": "action", "text": "Завести регламент" }),
+    )
+    .await;
+    let (_, typo) = post_json(
+        &app,
+        "/admin/notes",
+        json!({ "kind": "action", "text": "Опечатка" }),
+    )
+    .await;
+    let (status, _) = send(
+        &app,
+        Request::delete(format!("/admin/notes/{}", typo["id"]))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let notes = get_json(&app, "/admin/notes").await;
+    assert_eq!(notes.as_array().unwrap().len(), 2);
+    assert_eq!(notes[1]["id"], action["id"]);
+
+    // «Дальше» закрывает запись и фиксирует время обсуждения
+    post_empty(&app, "/admin/next").await;
+    assert!(lock(&state).discussion.is_none());
+    assert_eq!(get_json(&app, "/admin/notes").await, json!([]));
+    assert!(get_json(&app, "/api/current").await["discussion"].is_null());
+
+    let export = get_json(&app, "/admin/export").await;
+    let entry = &export[0];
+    assert_eq!(export.as_array().unwrap().len(), 1);
+    assert_eq!(entry["title"], "Первая");
+    assert_eq!(entry["shown"]["answer"], "врать+отложить");
+    assert_eq!(entry["discussion_secs"], 0);
+    assert_eq!(entry["notes"][1]["text"], "Завести регламент");
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::get("/admin/export?format=markdown")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let md = String::from_utf8(
+        to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec(),
+    )
+    .unwrap();
+    assert!(md.contains("## 1. Первая"), "{md}");
+    assert!(md.contains("> врать+отложить"), "{md}");
+    assert!(md.contains("- Все молчат\n"), "{md}");
+    assert!(md.contains("- [ ] Завести регламент"), "{md}");
+    assert!(!md.contains("Опечатка"));
+
+    let (status, _) = send(
+        &app,
+        Request::get("/admin/export?format=xml")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn export_flags_overridden_answers() {
+    let (app, _) = test_app();
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    post_json(&app, "/admin/show", json!({ "answer": "Своими словами" })).await;
+    post_empty(&app, "/admin/next").await;
+    get_json(&app, "/admin/show").await;
+
+    let export = get_json(&app, "/admin/export").await;
+    assert_eq!(export[0]["shown"]["overridden"], true);
+    assert_eq!(export[1]["shown"]["overridden"], false);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::get("/admin/export?format=markdown")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let md = String::from_utf8(
+        to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(md.matches("Ответ поправлен ведущим").count(), 1, "{md}");
+    assert!(
+        md.contains("> Своими словами\n\n_Ответ поправлен ведущим_"),
+        "{md}"
+    );
+}
+
+#[tokio::test]
+async fn discussion_starts_by_itself_with_room_default() {
+    let (app, state) = test_app();
+    lock(&state).discussion_secs = Some(120);
+    click(&app, "freeze", 1).await;
+    get_json(&app, "/admin/show").await;
+    assert_eq!(
+        get_json(&app, "/api/current").await["discussion"]["secs"],
+        120
+    );
+    post_empty(&app, "/admin/reset").await;
+    assert!(get_json(&app, "/api/current").await["discussion"].is_null());
+}
+
+// ===================== Выгрузка в трекер =====================
+
+#[test]
+fn tracker_config_is_checked_at_load() {
+    let config = |url: &str, extra: &str| {
+        format!(r#"{{"url": "{url}", "body": {{"title": "{{text}}"}}{extra}}}"#)
+    };
+    let ok = tracker::Config::from_json(&config(
+        "https://t.example/{number}",
+        r#", "headers": {"Authorization": "Bearer {env:PATH}"}"#,
+    ))
+    .unwrap();
+    assert_eq!((ok.method.as_str(), ok.retries), ("POST", 2));
+
+    let err = |json: String| tracker::Config::from_json(&json).unwrap_err();
+    assert!(err(config("ftp://t.example", "")).contains("http://"));
+    assert!(err(config("https://t.example", r#", "retries": 1000"#)).contains("at most 5"));
+    assert!(err(config("https://t.example", r#", "timeout_ms": 0"#)).contains("timeout_ms"));
+    assert!(err(config(
+        "https://t.example",
+        r#", "headers": {"X": "{who}"}"#
+    ))
+    .contains("unknown placeholder {who}, expected one of: text, situation"));
+    assert!(err(config(
+        "https://t.example",
+        r#", "headers": {"X": "{env:TEAM_CLICKER_SURELY_UNSET}"}"#
+    ))
+    .contains("TEAM_CLICKER_SURELY_UNSET is not set"));
+    assert!(err(config(
+        "https://t.example",
+        r#", "headers": {"bad name": "x"}"#
+    ))
+    .contains("bad header name"));
+    assert!(
+        err(r#"{"url": "https://t.example", "body": {"a": ["{oops"]}}"#.to_string())
+            .contains("body.a[0]")
+    );
+}
+
+// трекер-заглушка: запоминает запросы, отвечает по тексту задачи
+#[derive(Default)]
+struct MockTracker {
+    requests: Vec<(String, Value)>,
+    flaky_failures: u32,
+}
+
+async fn mock_issue(
+    State(mock): State<Arc<Mutex<MockTracker>>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> StatusCode {
+    let mut mock = mock.lock().unwrap();
+    let note = headers["x-note"].to_str().unwrap().to_string();
+    mock.requests.push((note, body.clone()));
+    match body["title"].as_str() {
+        Some("Плохой") => StatusCode::UNPROCESSABLE_ENTITY,
+        Some(t) if t.starts_with("Сказать") && mock.flaky_failures < 1 => {
+            mock.flaky_failures += 1;
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        _ => StatusCode::CREATED,
+    }
+}
+
+#[tokio::test]
+async fn tracker_push_retries_and_reports() {
+    let (app, state) = test_app();
+    let (status, body) = send(
+        &app,
+        Request::post("/admin/tracker/push")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    let mock = Arc::new(Mutex::new(MockTracker::default()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Router::new()
+        .route("/issues", post(mock_issue))
+        .with_state(mock.clone());
+    tokio::spawn(async move { axum::serve(listener, server).await.unwrap() });
+
+    let config = json!({
+        "url": format!("http://{addr}/issues"),
+        "headers": { "X-Note": "{note_id}" },
+        "body": {
+            "title": "{text}",
+            "description": "Ситуация {number}: {situation}. Ответ: {answer}",
+            "labels": ["retro"],
+        },
+        "retries": 2,
+        "backoff_ms": 1,
+    });
+    lock(&state).tracker = Some(Arc::new(
+        tracker::Config::from_json(&config.to_string()).unwrap(),
+    ));
+
+    click(&app, "lie", 2).await;
+    click(&app, "delay", 1).await;
+    get_json(&app, "/admin/show").await;
+    let action = |text: &str| json!({ "kind": "action", "text": text });
+    post_json(&app, "/admin/notes", action(r#"Сказать "правду""#)).await;
+    post_json(&app, "/admin/notes", action("Плохой")).await;
+    post_json(&app, "/admin/notes", json!({ "text": "просто заметка" })).await;
+    post_empty(&app, "/admin/next").await;
+    post_json(&app, "/admin/notes", action("Починить доки")).await;
+    assert_eq!(get_json(&app, "/admin/tracker").await["pending"], 3);
+
+    let report = post_empty(&app, "/admin/tracker/push").await;
+    assert_eq!(
+        (report["delivered"].as_u64(), report["failed"].as_u64()),
+        (Some(2), Some(1))
+    );
+    let attempts: Vec<_> = report["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| {
+            (
+                d["ok"].as_bool().unwrap(),
+                d["attempts"].as_u64().unwrap(),
+                d["status"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    // 503 повторили, 422 — нет
+    assert_eq!(attempts, [(true, 2, 201), (false, 1, 422), (true, 1, 201)]);
+
+    {
+        let mock = mock.lock().unwrap();
+        assert_eq!(mock.requests.len(), 4);
+        let (note, body) = &mock.requests[0];
+        assert_eq!(note, "1");
+        assert_eq!(body["title"], r#"Сказать "правду""#);
+        assert_eq!(
+            body["description"],
+            "Ситуация 1: Первая. Ответ: врать+отложить"
+        );
+        assert_eq!(body["labels"], json!(["retro"]));
+        assert_eq!(
+            mock.requests[3].1["description"],
+            "Ситуация 2: Вторая. Ответ: "
+        );
+    }
+
+    // повторная выгрузка не дублирует заведённое
+    let report = post_empty(&app, "/admin/tracker/push").await;
+    assert_eq!(
+        (
+            report["delivered"].as_u64(),
+            report["failed"].as_u64(),
+            report["skipped"].as_u64()
+        ),
+        (Some(0), Some(1), Some(2))
+    );
+    assert_eq!(mock.lock().unwrap().requests.len(), 5);
+    let status = get_json(&app, "/admin/tracker").await;
+    assert_eq!(
+        (status["pending"].as_u64(), status["pushed"].as_u64()),
+        (Some(1), Some(2))
+    );
+    assert_eq!(status["report"]["skipped"], 2);
+    assert_eq!(
+        get_json(&app, "/admin/export").await[0]["notes"][0]["pushed"],
+        true
+    );
+}
+
+#[tokio::test]
+async fn tracker_encodes_url_and_does_not_retry_broken_requests() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Router::new()
+        .route(
+            "/issues/:situation",
+            post(
+                |State(seen): State<Arc<Mutex<Vec<String>>>>, Path(situation): Path<String>| async move {
+                    seen.lock().unwrap().push(situation);
+                    StatusCode::CREATED
+                },
+            ),
+        )
+        .with_state(seen.clone());
+    tokio::spawn(async move { axum::serve(listener, server).await.unwrap() });
+
+    let config = json!({
+        "url": format!("http://{addr}/issues/{{situation}}"),
+        "headers": { "X-Text": "{text}" },
+        "body": { "title": "{text}" },
+        "retries": 2,
+        "backoff_ms": 1,
+    });
+    let config = tracker::Config::from_json(&config.to_string()).unwrap();
+    let item = |note_id, text: &str| tracker::Item {
+        note_id,
+        text: text.to_string(),
+        situation: "Да/нет? & 100%".to_string(),
+        number: 1,
+        answer: None,
+    };
+    let report = tracker::push(&config, &[item(1, "Сделать"), item(2, "две\nстроки")]).await;
+
+    assert!(report.items[0].ok);
+    assert_eq!(*seen.lock().unwrap(), ["Да/нет? & 100%"]);
+    // перевод строки в заголовке — запрос не собрать, повторять бессмысленно
+    let broken = &report.items[1];
+    assert_eq!(
+        (broken.ok, broken.attempts, broken.status),
+        (false, 1, None)
+    );
+    assert!(broken.error.is_some());
+}
@@ -0,0 +1,333 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::template;
+
+// ===================== Выгрузка договорённостей в трекер =====================
+//
+// В конце сессии договорённости (заметки вида action) уходят в таск-трекер —
+// по задаче на каждую. Запрос описывается шаблоном в JSON-файле (TRACKER_CONFIG):
+//
+//   {
+//     "url": "https://tracker.example/api/issues",
+//     "headers": { "Authorization": "Bearer {env:TRACKER_TOKEN}" },
+//     "body": { "title": "{text}", "description": "Ретро, ситуация {number}: {situation}" },
+//     "retries": 2
+//   }
+//
+// Подстановки: {text}, {situation}, {number}, {answer}, {note_id} и {env:ИМЯ}
+// для секретов из окружения. В теле они работают только внутри строк, так что
+// JSON всегда остаётся валидным; в url значения кодируются (percent-encoding).
+
+const ITEM_VARIABLES: &[&str] = &["text", "situation", "number", "answer", "note_id"];
+
+// ответ трекера в отчёте обрезаем — он бывает огромной HTML-страницей
+const MAX_RESPONSE_LEN: usize = 300;
+
+// выгрузка идёт внутри запроса ведущего: на задачу не больше
+// (MAX_RETRIES + 1) × MAX_TIMEOUT_MS плюс паузы
+const MAX_RETRIES: u32 = 5;
+const MAX_TIMEOUT_MS: u64 = 30_000;
+const MAX_BACKOFF_MS: u64 = 5_000;
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+fn default_retries() -> u32 {
+    2
+}
+
+fn default_backoff_ms() -> u64 {
+    500
+}
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Config {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Value,
+    // повторы сверх первой попытки; пауза удваивается с каждым
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn is_placeholder(name: &str) -> bool {
+    ITEM_VARIABLES.contains(&name) || name.starts_with("env:")
+}
+
+fn check(what: &str, src: &str) -> Result<(), String> {
+    let expected = format!("{}, env:NAME", ITEM_VARIABLES.join(", "));
+    let names = template::placeholders(src, &is_placeholder, &expected)
+        .map_err(|e| format!("{what}: {e}"))?;
+    // секреты проверяем сразу, а не на первой выгрузке в конце сессии
+    for var in names.iter().filter_map(|n| n.strip_prefix("env:")) {
+        if std::env::var(var).is_err() {
+            return Err(format!("{what}: environment variable {var} is not set"));
+        }
+    }
+    Ok(())
+}
+
+fn check_value(path: &str, v: &Value) -> Result<(), String> {
+    match v {
+        Value::String(s) => check(path, s),
+        Value::Array(list) => list
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, v)| check_value(&format!("{path}[{i}]"), v)),
+        Value::Object(map) => map
+            .iter()
+            .try_for_each(|(k, v)| check_value(&format!("{path}.{k}"), v)),
+        _ => Ok(()),
+    }
+}
+
+impl Config {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config: Config = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+            return Err(format!(
+                "url must start with http:// or https://, got {}",
+                config.url
+            ));
+        }
+        reqwest::Method::from_bytes(config.method.as_bytes())
+            .map_err(|_| format!("bad method {}", config.method))?;
+        if config.retries > MAX_RETRIES {
+            return Err(format!(
+                "retries must be at most {MAX_RETRIES}, got {}",
+                config.retries
+            ));
+        }
+        if !(1..=MAX_TIMEOUT_MS).contains(&config.timeout_ms) {
+            return Err(format!(
+                "timeout_ms must be from 1 to {MAX_TIMEOUT_MS}, got {}",
+                config.timeout_ms
+            ));
+        }
+        if config.backoff_ms > MAX_BACKOFF_MS {
+            return Err(format!(
+                "backoff_ms must be at most {MAX_BACKOFF_MS}, got {}",
+                config.backoff_ms
+            ));
+        }
+        check("url", &config.url)?;
+        for (name, value) in &config.headers {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("bad header name {name}"))?;
+            check(&format!("header {name}"), value)?;
+        }
+        check_value("body", &config.body)?;
+        Ok(config)
+    }
+}
+
+/// Договорённость, которую надо завести в трекере.
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub note_id: u64,
+    pub text: String,
+    pub situation: String,
+    // номер ситуации в сессии, с единицы
+    pub number: usize,
+    pub answer: Option<String>,
+}
+
+impl Item {
+    fn get(&self, name: &str) -> String {
+        if let Some(var) = name.strip_prefix("env:") {
+            return std::env::var(var).unwrap_or_default();
+        }
+        match name {
+            "text" => self.text.clone(),
+            "situation" => self.situation.clone(),
+            "number" => self.number.to_string(),
+            "answer" => self.answer.clone().unwrap_or_default(),
+            "note_id" => self.note_id.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn render(&self, src: &str) -> String {
+        template::render_with(src, &is_placeholder, &|name| self.get(name))
+    }
+
+    // «/», «?» или «&» в тексте договорённости не должны менять адрес
+    fn render_url(&self, src: &str) -> String {
+        template::render_with(src, &is_placeholder, &|name| {
+            percent_encode(&self.get(name))
+        })
+    }
+
+    fn render_value(&self, v: &Value) -> Value {
+        match v {
+            Value::String(s) => Value::String(self.render(s)),
+            Value::Array(list) => Value::Array(list.iter().map(|v| self.render_value(v)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.render_value(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Delivery {
+    pub note_id: u64,
+    pub text: String,
+    pub ok: bool,
+    pub attempts: u32,
+    // HTTP-статус последней попытки, если до трекера достучались
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub response: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    pub delivered: usize,
+    pub failed: usize,
+    // уже заведены прошлыми выгрузками
+    pub skipped: usize,
+    pub items: Vec<Delivery>,
+}
+
+// повторяем сетевые сбои, 5xx и 429; прочие 4xx — ошибка шаблона, повтор не поможет
+fn retryable(status: Option<reqwest::StatusCode>) -> bool {
+    status.is_none_or(|s| s.is_server_error() || s == reqwest::StatusCode::TOO_MANY_REQUESTS)
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+fn truncate(text: String) -> String {
+    match text.char_indices().nth(MAX_RESPONSE_LEN) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text,
+    }
+}
+
+async fn deliver(client: &reqwest::Client, config: &Config, item: &Item) -> Delivery {
+    // проверено в from_json
+    let method =
+        reqwest::Method::from_bytes(config.method.as_bytes()).unwrap_or(reqwest::Method::POST);
+    let url = item.render_url(&config.url);
+    let body = item.render_value(&config.body).to_string();
+    let mut delivery = Delivery {
+        note_id: item.note_id,
+        text: item.text.clone(),
+        ok: false,
+        attempts: 0,
+        status: None,
+        error: None,
+        response: None,
+    };
+    for attempt in 0..=config.retries {
+        if attempt > 0 {
+            let pause = config.backoff_ms.saturating_mul(1 << (attempt - 1).min(10));
+            tokio::time::sleep(Duration::from_millis(pause)).await;
+        }
+        delivery.attempts = attempt + 1;
+        let mut req = client
+            .request(method.clone(), &url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        for (name, value) in &config.headers {
+            req = req.header(name.as_str(), item.render(value));
+        }
+        // битый адрес или заголовок (например, перевод строки из текста) — повтор не поможет
+        let req = match req.build() {
+            Ok(req) => req,
+            Err(e) => {
+                delivery.error = Some(e.to_string());
+                break;
+            }
+        };
+        let status = match client.execute(req).await {
+            Ok(resp) => {
+                let status = resp.status();
+                delivery.status = Some(status.as_u16());
+                delivery.response = resp.text().await.ok().map(truncate);
+                if status.is_success() {
+                    delivery.ok = true;
+                    delivery.error = None;
+                    return delivery;
+                }
+                delivery.error = Some(format!("tracker answered {status}"));
+                Some(status)
+            }
+            Err(e) => {
+                delivery.status = None;
+                delivery.response = None;
+                delivery.error = Some(e.to_string());
+                None
+            }
+        };
+        if !retryable(status) {
+            break;
+        }
+    }
+    delivery
+}
+
+/// Заводит задачи по очереди (трекеры не любят залпы) и собирает отчёт.
+pub async fn push(config: &Config, items: &[Item]) -> Report {
+    let mut report = Report::default();
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            for item in items {
+                report.failed += 1;
+                report.items.push(Delivery {
+                    note_id: item.note_id,
+                    text: item.text.clone(),
+                    ok: false,
+                    attempts: 0,
+                    status: None,
+                    error: Some(e.to_string()),
+                    response: None,
+                });
+            }
+            return report;
+        }
+    };
+    for item in items {
+        let delivery = deliver(&client, config, item).await;
+        if delivery.ok {
+            report.delivered += 1;
+        } else {
+            report.failed += 1;
+        }
+        report.items.push(delivery);
+    }
+    report
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::{random_u64, Reaction};
+
+// ===================== Варианты ответов =====================
+
+/// Как выбирать вариант ответа, если на пару реакций их несколько.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VariantStrategy {
+    Random,
+    RoundRobin,
+    LeastRecentlyShown,
+}
+
+impl VariantStrategy {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "random" => Some(VariantStrategy::Random),
+            "round-robin" => Some(VariantStrategy::RoundRobin),
+            "lru" | "least-recently-shown" => Some(VariantStrategy::LeastRecentlyShown),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct PairUsage {
+    next: usize,          // для round-robin
+    last_shown: Vec<u64>, // для LRU: «такт» последнего показа, 0 — ещё не показывали
+}
+
+// ключ: (индекс ситуации, пара реакций)
+type UsageKey = (usize, (Reaction, Reaction));
+
+#[derive(Clone)]
+pub struct VariantPicker {
+    pub strategy: VariantStrategy,
+    usage: HashMap<UsageKey, PairUsage>,
+    tick: u64,
+}
+
+impl VariantPicker {
+    pub fn new(strategy: VariantStrategy) -> Self {
+        VariantPicker {
+            strategy,
+            usage: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Индекс варианта из `len` доступных. История показов переживает next/reset,
+    /// так что при повторном проходе колоды ответы не повторяются подряд.
+    pub fn pick(&mut self, situation: usize, pair: (Reaction, Reaction), len: usize) -> usize {
+        let idx = match self.strategy {
+            VariantStrategy::Random if len > 1 => (random_u64() % len as u64) as usize,
+            _ => self.peek(situation, pair, len),
+        };
+        self.mark_shown(situation, pair, len, idx);
+        idx
+    }
+
+    /// Какой вариант выдал бы `pick`, без записи в историю — для предпросмотра.
+    /// Случайный выбор заранее не угадать: тогда — наименее затёртый, чтобы черновик не мигал.
+    pub fn peek(&self, situation: usize, pair: (Reaction, Reaction), len: usize) -> usize {
+        if len <= 1 {
+            return 0;
+        }
+        let usage = self.usage.get(&(situation, pair));
+        match self.strategy {
+            VariantStrategy::RoundRobin => usage.map_or(0, |u| u.next % len),
+            // min_by_key берёт первый минимум, так что непоказанные идут по порядку
+            VariantStrategy::Random | VariantStrategy::LeastRecentlyShown => (0..len)
+                .min_by_key(|&i| {
+                    usage
+                        .and_then(|u| u.last_shown.get(i).copied())
+                        .unwrap_or(0)
+                })
+                .unwrap_or(0),
+        }
+    }
+
+    fn mark_shown(&mut self, situation: usize, pair: (Reaction, Reaction), len: usize, idx: usize) {
+        if len <= 1 {
+            return;
+        }
+        self.tick += 1;
+        let usage = self.usage.entry((situation, pair)).or_default();
+        usage.last_shown.resize(len, 0);
+        usage.next = idx + 1;
+        usage.last_shown[idx] = self.tick;
+    }
+}
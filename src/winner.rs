@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{idx_to_reaction, random_u64, top_two, Reaction};
+
+// ===================== Выбор пары =====================
+//
+// По раскладу голосов решаем, на какую пару реакций отвечать. Стратегию
+// выбирает комната (/admin/winner, WINNER), ситуация в колоде может задать свою.
+
+pub trait WinnerStrategy {
+    /// (лидер, второй): порядок важен — лидера угадывают в прогнозах.
+    fn select(&self, counts: &[u64; 3]) -> (Reaction, Reaction);
+
+    /// Какую пару ждём при текущем раскладе — для подсказки «Дальше» до показа.
+    /// Случайным стратегиям тут нельзя бросать кубик: подсказка бы мигала.
+    fn expected(&self, counts: &[u64; 3]) -> (Reaction, Reaction) {
+        self.select(counts)
+    }
+}
+
+/// Как раньше: две реакции с наибольшим числом голосов.
+pub struct TopTwo;
+
+impl WinnerStrategy for TopTwo {
+    fn select(&self, counts: &[u64; 3]) -> (Reaction, Reaction) {
+        top_two(counts)
+    }
+}
+
+/// Лидер — случайно пропорционально голосам, второй — так же из оставшихся.
+pub struct WeightedRandom {
+    // источник случайности подменяется в тестах
+    pub roll: fn() -> u64,
+}
+
+impl Default for WeightedRandom {
+    fn default() -> Self {
+        WeightedRandom { roll: random_u64 }
+    }
+}
+
+impl WeightedRandom {
+    fn draw(&self, counts: &[u64; 3], skip: Option<usize>) -> usize {
+        let candidates: Vec<usize> = (0..3).filter(|&i| Some(i) != skip).collect();
+        let total: u64 = candidates.iter().map(|&i| counts[i]).sum();
+        // голосов нет — все равновероятны
+        if total == 0 {
+            return candidates[((self.roll)() % candidates.len() as u64) as usize];
+        }
+        let mut ticket = (self.roll)() % total;
+        for &i in &candidates {
+            if ticket < counts[i] {
+                return i;
+            }
+            ticket -= counts[i];
+        }
+        candidates[candidates.len() - 1]
+    }
+}
+
+impl WinnerStrategy for WeightedRandom {
+    fn select(&self, counts: &[u64; 3]) -> (Reaction, Reaction) {
+        let first = self.draw(counts, None);
+        let second = self.draw(counts, Some(first));
+        (idx_to_reaction(first), idx_to_reaction(second))
+    }
+
+    fn expected(&self, counts: &[u64; 3]) -> (Reaction, Reaction) {
+        top_two(counts)
+    }
+}
+
+/// Режим «назло залу»: две реакции с наименьшим числом голосов.
+pub struct LeastPopular;
+
+impl WinnerStrategy for LeastPopular {
+    fn select(&self, counts: &[u64; 3]) -> (Reaction, Reaction) {
+        // при равенстве — порядок реакций, как в top_two
+        let mut idx = [0usize, 1, 2];
+        idx.sort_by_key(|&i| counts[i]);
+        (idx_to_reaction(idx[0]), idx_to_reaction(idx[1]))
+    }
+}
+
+/// Ведущий заранее решил, какую пару показать, как бы ни голосовали.
+pub struct AdminOverride {
+    pub pair: (Reaction, Reaction),
+}
+
+impl WinnerStrategy for AdminOverride {
+    fn select(&self, _counts: &[u64; 3]) -> (Reaction, Reaction) {
+        self.pair
+    }
+}
+
+/// Настройка стратегии: то, что хранится в комнате и в колоде.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "kebab-case")]
+pub enum WinnerRule {
+    #[default]
+    TopTwo,
+    WeightedRandom,
+    LeastPopular,
+    Override {
+        pair: (Reaction, Reaction),
+    },
+}
+
+impl WinnerRule {
+    // пару для override из переменной окружения не задать — только из админки
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "top-two" => Some(WinnerRule::TopTwo),
+            "weighted-random" => Some(WinnerRule::WeightedRandom),
+            "least-popular" => Some(WinnerRule::LeastPopular),
+            _ => None,
+        }
+    }
+
+    pub fn strategy(self) -> Box<dyn WinnerStrategy> {
+        match self {
+            WinnerRule::TopTwo => Box::new(TopTwo),
+            WinnerRule::WeightedRandom => Box::new(WeightedRandom::default()),
+            WinnerRule::LeastPopular => Box::new(LeastPopular),
+            WinnerRule::Override { pair } => Box::new(AdminOverride { pair }),
+        }
+    }
+}